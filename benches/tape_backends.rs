@@ -0,0 +1,72 @@
+//! Compares the dense and sparse tape backends on programs which travel far across the tape.
+//! Run with `cargo bench --bench tape_backends`.
+
+use std::time::Instant;
+use simple_turing_machine::compiler::layers::base::BaseLayer;
+use simple_turing_machine::compiler::layers::primitive::PrimitiveLayer;
+use simple_turing_machine::compiler::layers::program_builder::ProgramBuilder;
+use simple_turing_machine::compiler::turing_compiler::TuringCompiler;
+use simple_turing_machine::enums::movement::Movement;
+use simple_turing_machine::machine::sparse_tape::SparseTape;
+use simple_turing_machine::machine::tape::Tape;
+use simple_turing_machine::machine::turing_machine::TuringMachine;
+use simple_turing_machine::machine::turing_program::TuringProgram;
+use simple_turing_machine::machine::turing_tape::TuringTape;
+
+const DISTANCE: usize = 1 << 22;
+
+fn main() {
+    println!("{:<24} {:<8} {:>12} {:>14}", "scenario", "backend", "time (ms)", "memory (bytes)");
+    run_scenario::<TuringTape>("far right scan", "dense", far_right_scan);
+    run_scenario::<SparseTape>("far right scan", "sparse", far_right_scan);
+    run_scenario::<TuringTape>("far left scan", "dense", far_left_scan);
+    run_scenario::<SparseTape>("far left scan", "sparse", far_left_scan);
+    run_scenario::<TuringTape>("scattered marks", "dense", scattered_marks);
+    run_scenario::<SparseTape>("scattered marks", "sparse", scattered_marks);
+}
+
+fn run_scenario<T: Tape>(name: &str, backend: &str, scenario: fn() -> (TuringMachine<T>, usize)) {
+    let (mut tm, steps) = scenario();
+    let start = Instant::now();
+    for _ in 0..steps {
+        if !tm.program_step() {
+            break;
+        }
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{:<24} {:<8} {:>12.1} {:>14}",
+        name,
+        backend,
+        elapsed.as_secs_f64() * 1000.0,
+        tm.tape.get_memory_usage()
+    );
+}
+
+fn build_scan_program(movement: Movement) -> TuringProgram {
+    let mut compiler = TuringCompiler::default();
+    let scan_state = compiler.allocate_state();
+    let done = compiler.halt(None);
+    compiler.scan_single(true, movement, Movement::Stay, Some(scan_state), Some(done));
+    compiler.get_program()
+}
+
+/// Scans for a mark on an empty tape, so the head keeps walking to the right.
+fn far_right_scan<T: Tape>() -> (TuringMachine<T>, usize) {
+    (TuringMachine::new(T::default()).with_program(build_scan_program(Movement::Right)), DISTANCE)
+}
+
+/// Scans for a mark on an empty tape to the left, which makes the tape grow to the left over and over again.
+fn far_left_scan<T: Tape>() -> (TuringMachine<T>, usize) {
+    (TuringMachine::new(T::default()).with_program(build_scan_program(Movement::Left)), DISTANCE)
+}
+
+/// Marks every 4096th cell, so most of the visited tape stays blank.
+fn scattered_marks<T: Tape>() -> (TuringMachine<T>, usize) {
+    let mut compiler = TuringCompiler::default();
+    let mark_state = compiler.allocate_state();
+    let move_state = compiler.allocate_state();
+    compiler.mark_and_move_right(Some(mark_state), Some(move_state));
+    compiler.move_right_x(4095, Some(move_state), Some(mark_state));
+    (TuringMachine::new(T::default()).with_program(compiler.get_program()), DISTANCE * 4)
+}
@@ -1,4 +1,15 @@
 pub mod primitive;
 pub mod program_builder;
 pub mod base;
-pub mod pattern;
\ No newline at end of file
+pub mod pattern;
+pub mod fragment;
+pub mod field;
+pub mod integer;
+pub mod comparison;
+pub mod bitwise;
+pub mod region;
+pub mod tape_shift;
+pub mod data_structure;
+pub mod layout;
+pub mod regex;
+pub mod counter;
\ No newline at end of file
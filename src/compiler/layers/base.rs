@@ -4,10 +4,12 @@ use crate::machine::state::State;
 
 pub trait BaseLayer: PrimitiveLayer {
     /// Creates a chained loop which will repeat a given build instruction n times.
+    /// This always unrolls, every iteration gets its own states. To count on the tape instead when that takes fewer
+    /// states, use [`repeat_n`](crate::compiler::layers::counter::CounterLayer::repeat_n).
     ///
     /// # Arguments
     ///
-    /// * `n`: How many times the build instruction should be repeated.
+    /// * `n`: How many times the build instruction should be repeated, zero passes straight through to the end state.
     /// * `start_state`: The start state of the loop.
     /// * `end_state`: The end state of the loop.
     /// * `build_iteration`: A function which takes in the current iteration count, a start_state and end_state and returns a start_state and end_state.
@@ -16,7 +18,7 @@ pub trait BaseLayer: PrimitiveLayer {
     ///
     /// # Examples
     /// Repeats move_right x times.
-    /// ```
+    /// ```ignore
     /// let (start_loop_state, end_loop_state) = self.chained_loop(
     ///      x,
     ///      Some(start_state),
@@ -33,6 +35,9 @@ pub trait BaseLayer: PrimitiveLayer {
         end_state: Option<State>,
         build_iteration: impl Fn(&mut Self, usize, Option<State>, Option<State>) -> (State, State),
     ) -> (State, State) {
+        if n == 0 {
+            return self.idle(start_state, end_state);
+        }
         if n == 1 {
             return build_iteration(self, 0, start_state, end_state);
         }
@@ -64,6 +69,9 @@ pub trait BaseLayer: PrimitiveLayer {
     }
 
     /// The current state will move the head by x to the right, then transition to the next state.
+    /// This takes x states, so `move_right_x(1000, ..)` still builds 1000 of them. A counter can't shorten it, since
+    /// [`repeat_n`](crate::compiler::layers::counter::CounterLayer::repeat_n) needs the head back in place after every
+    /// iteration.
     fn move_right_x(&mut self, x: usize, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
         let start_state = current_state.unwrap_or_else(|| self.allocate_state());
         let end_state = next_state.unwrap_or_else(|| self.allocate_state());
@@ -81,6 +89,7 @@ pub trait BaseLayer: PrimitiveLayer {
     }
 
     /// The current state will move the head by x to the left, then transition to the next state.
+    /// Like [`move_right_x`](BaseLayer::move_right_x) this takes x states.
     fn move_left_x(&mut self, x: usize, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
         let start_state = current_state.unwrap_or_else(|| self.allocate_state());
         let end_state = next_state.unwrap_or_else(|| self.allocate_state());
@@ -106,7 +115,7 @@ pub trait BaseLayer: PrimitiveLayer {
     }
 
     /// The current state will move the head in the given direction till it finds the given bit, then transition to the next state.
-    /// If the bit is not found this results in an endless loop. Counters are not a thing on this primitive level yet.
+    /// If the bit is not found this results in an endless loop, see [`scan_single_bounded`](BaseLayer::scan_single_bounded).
     fn scan_single(
         &mut self,
         target_bit: bool,
@@ -139,6 +148,60 @@ pub trait BaseLayer: PrimitiveLayer {
         (start_state, end_state)
     }
 
+    /// Like [`scan_single`](BaseLayer::scan_single) but reads at most `max_distance` cells, starting with the one under
+    /// the head. If the bit isn't among them, the head returns to where it started and transitions to the not found state.
+    ///
+    /// The cells are counted by an unrolled chain, so this takes O(max_distance) states.
+    #[allow(clippy::too_many_arguments)]
+    fn scan_single_bounded(
+        &mut self,
+        target_bit: bool,
+        max_distance: usize,
+        scan_movement: Movement,
+        final_movement: Movement,
+        current_state: Option<State>,
+        found_state: Option<State>,
+        not_found_state: Option<State>,
+    ) -> (State, State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let found_state = found_state.unwrap_or_else(|| self.allocate_state());
+        let not_found_state = not_found_state.unwrap_or_else(|| self.allocate_state());
+
+        if max_distance == 0 {
+            self.idle(Some(start_state), Some(not_found_state));
+            return (start_state, found_state, not_found_state);
+        }
+
+        let (give_up_movement, give_up_state) = self.give_up_scan(max_distance, scan_movement, not_found_state);
+        let mut read_state = start_state;
+        for i in 0..max_distance {
+            let (else_movement, else_state) = if i < max_distance - 1 {
+                (scan_movement, self.allocate_state())
+            } else {
+                (give_up_movement, give_up_state)
+            };
+            self.branch_when(target_bit, final_movement, else_movement, Some(read_state), Some(found_state), Some(else_state));
+            read_state = else_state;
+        }
+
+        (start_state, found_state, not_found_state)
+    }
+
+    /// The movement and state after a bounded scan read its last cell without success, which rewind the head by the
+    /// `max_distance - 1` cells it moved, then transition to the not found state.
+    fn give_up_scan(&mut self, max_distance: usize, scan_movement: Movement, not_found_state: State) -> (Movement, State) {
+        if max_distance <= 1 {
+            return (Movement::Stay, not_found_state);
+        }
+
+        let rewind_movement = scan_movement.get_opposite();
+        let mut state = not_found_state;
+        for _ in 2..max_distance {
+            (state, _) = self.move_in_direction(rewind_movement, None, Some(state));
+        }
+        (rewind_movement, state)
+    }
+
     fn branch_when(
         &mut self,
         target_bit: bool,
@@ -319,6 +382,7 @@ mod tests {
     use crate::compiler::layers::program_builder::ProgramBuilder;
     use crate::compiler::turing_compiler::TuringCompiler;
     use crate::machine::turing_machine::TuringMachine;
+    use crate::machine::tape::Tape;
     use crate::machine::turing_tape::TuringTape;
     use super::*;
     
@@ -356,6 +420,22 @@ mod tests {
         assert!(!tm.read());
     }
     
+    #[test]
+    fn test_chained_loop_zero() {
+        let mut compiler = TuringCompiler::default();
+        let start_state = compiler.allocate_state();
+        let done = compiler.halt(None);
+        let (_, move_state) = compiler.chained_loop(0, Some(start_state), None, |compiler, _, iter_start, iter_end| {
+            compiler.mark(iter_start, iter_end)
+        });
+        compiler.move_right_x(0, Some(move_state), Some(done));
+
+        let mut tm = TuringMachine::default().with_program(compiler.get_program());
+        tm.run_program();
+        assert_eq!(tm.head, 0);
+        assert!(!tm.read());
+    }
+
     #[test]
     fn test_move_right_x() {
         let mut compiler = TuringCompiler::default();
@@ -397,6 +477,33 @@ mod tests {
         assert_eq!(tm.head, 2763);
     }
 
+    #[test]
+    fn test_scan_single_bounded() {
+        // The mark is 3 cells away, so it's found when reading at least 4 cells
+        for (max_distance, movement, head, expected) in [
+            (0, Movement::Right, 2, (false, 2)),
+            (3, Movement::Right, 2, (false, 2)),
+            (4, Movement::Right, 2, (true, 5)),
+            (9, Movement::Right, 2, (true, 5)),
+            (1, Movement::Left, 8, (false, 8)),
+            (3, Movement::Left, 8, (true, 6)),
+            (2, Movement::Left, 8, (false, 8)),
+        ] {
+            let mut compiler = TuringCompiler::default();
+            let scan_state = compiler.allocate_state();
+            let (_, found, not_found) = compiler.scan_single_bounded(true, max_distance, movement, Movement::Stay, Some(scan_state), None, None);
+            compiler.mark(Some(found), Some(State::halt()));
+            compiler.unmark(Some(not_found), Some(State::halt()));
+
+            let mut tm = TuringMachine::default()
+                .with_program(compiler.get_program())
+                .with_tape(TuringTape::from_bit_string("0000_0110_0"));
+            tm.set_head(head);
+            tm.run_program();
+            assert_eq!((tm.read(), tm.head), expected, "max_distance={} {:?}", max_distance, movement);
+        }
+    }
+
     #[test]
     fn test_branch_when() {
         let mut compiler = TuringCompiler::default();
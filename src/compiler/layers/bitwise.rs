@@ -0,0 +1,408 @@
+use crate::compiler::layers::field::FieldLayer;
+use crate::compiler::structures::field::Field;
+use crate::machine::state::State;
+
+/// Word-wide logic on fields at arbitrary offsets relative to the head, see [`Field`].
+/// The destination may overlap the sources, as long as it doesn't start above one of them and below another one it
+/// overlaps. The head returns to where it started.
+/// Shifting left moves bits towards the most significant end, like `<<`.
+pub trait BitwiseLayer: FieldLayer {
+    /// Combines the bits of x and y at every position with the given operation.
+    fn combine_n(
+        &mut self,
+        x: Field,
+        y: Field,
+        destination: Field,
+        operation: fn(bool, bool) -> bool,
+        current_state: Option<State>,
+        next_state: Option<State>,
+    ) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        self.walk_sources_into(&[x, y], destination, |_, _, bits| ((), operation(bits[0], bits[1])), start_state, end_state);
+
+        (start_state, end_state)
+    }
+
+    fn not_n(&mut self, x: Field, destination: Field, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        self.walk_sources_into(&[x], destination, |_, _, bits| ((), !bits[0]), start_state, end_state);
+
+        (start_state, end_state)
+    }
+
+    fn and_n(&mut self, x: Field, y: Field, destination: Field, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        self.combine_n(x, y, destination, |a, b| a & b, current_state, next_state)
+    }
+
+    fn or_n(&mut self, x: Field, y: Field, destination: Field, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        self.combine_n(x, y, destination, |a, b| a | b, current_state, next_state)
+    }
+
+    fn xor_n(&mut self, x: Field, y: Field, destination: Field, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        self.combine_n(x, y, destination, |a, b| a ^ b, current_state, next_state)
+    }
+
+    fn nand_n(&mut self, x: Field, y: Field, destination: Field, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        self.combine_n(x, y, destination, |a, b| !(a & b), current_state, next_state)
+    }
+
+    fn nor_n(&mut self, x: Field, y: Field, destination: Field, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        self.combine_n(x, y, destination, |a, b| !(a | b), current_state, next_state)
+    }
+
+    /// destination := source << k, the destination may overlap the source in any way.
+    fn shift_left_n(&mut self, source: Field, k: usize, destination: Field, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        // The lowest k bits read cells below the source, which are ignored, and the source reads as zero above its width
+        let shifted_source = Field::new(source.offset - k as isize, (source.width + k).min(destination.width));
+        self.walk_sources_into(&[shifted_source], destination, move |i, _, bits| ((), i >= k && bits[0]), start_state, end_state);
+
+        (start_state, end_state)
+    }
+
+    /// destination := source >> k, the destination may overlap the source in any way.
+    fn shift_right_n(&mut self, source: Field, k: usize, destination: Field, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        // The narrower source reads as zero above its width
+        let shifted_source = Field::new(source.offset + k as isize, source.width.saturating_sub(k));
+        self.walk_sources_into(&[shifted_source], destination, |_, _, bits| ((), bits[0]), start_state, end_state);
+
+        (start_state, end_state)
+    }
+
+    /// Rotates the source left by k into the destination, which has to be either the source itself or disjoint from it.
+    /// In place this rotates by one bit k times.
+    fn rotate_left_n(&mut self, source: Field, k: usize, destination: Field, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        let width = source.width;
+        let k = if width == 0 { 0 } else { k % width };
+        assert_eq!(width, destination.width, "Rotating needs a destination as wide as the source");
+
+        if source == destination {
+            if k == 0 {
+                return self.idle(Some(start_state), Some(end_state));
+            }
+            self.chained_loop(k, Some(start_state), Some(end_state), |compiler, _, iter_start, iter_end| {
+                compiler.rotate_once(source, true, iter_start, iter_end)
+            });
+        } else {
+            assert!(!source.overlaps(&destination), "Rotating needs the destination to be the source or disjoint from it");
+            let low_state = self.allocate_state();
+            let copy = |_, _, bits: &[bool]| ((), bits[0]);
+            self.walk_fields(
+                &[Field::new(source.offset, width - k)],
+                Some(Field::new(destination.offset + k as isize, width - k)),
+                (),
+                copy,
+                Some(start_state),
+                |_| low_state,
+            );
+            self.walk_fields(
+                &[Field::new(source.offset + (width - k) as isize, k)],
+                Some(Field::new(destination.offset, k)),
+                (),
+                copy,
+                Some(low_state),
+                |_| end_state,
+            );
+        }
+
+        (start_state, end_state)
+    }
+
+    /// Rotates the source right by k into the destination, which has to be either the source itself or disjoint from it.
+    fn rotate_right_n(&mut self, source: Field, k: usize, destination: Field, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let width = source.width;
+        let k = if width == 0 { 0 } else { k % width };
+
+        if source == destination {
+            let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+            let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+            if k == 0 {
+                return self.idle(Some(start_state), Some(end_state));
+            }
+            self.chained_loop(k, Some(start_state), Some(end_state), |compiler, _, iter_start, iter_end| {
+                compiler.rotate_once(source, false, iter_start, iter_end)
+            });
+            (start_state, end_state)
+        } else {
+            self.rotate_left_n(source, (width - k) % width.max(1), destination, current_state, next_state)
+        }
+    }
+
+    /// Walks the sources into the destination, starting at the end of the destination which overlaps a source first,
+    /// so no source bit is overwritten before it was read.
+    fn walk_sources_into(
+        &mut self,
+        sources: &[Field],
+        destination: Field,
+        step: impl Fn(usize, (), &[bool]) -> ((), bool),
+        current_state: State,
+        next_state: State,
+    ) {
+        let overlapping = || sources.iter().filter(|source| source.overlaps(&destination));
+        let downwards = overlapping().any(|source| source.offset < destination.offset);
+        let upwards = overlapping().any(|source| source.offset > destination.offset);
+        assert!(!(downwards && upwards), "The destination can't start above one source and below another one it overlaps");
+
+        if downwards {
+            self.walk_fields_downwards(sources, Some(destination), (), step, Some(current_state), |_| next_state);
+        } else {
+            self.walk_fields(sources, Some(destination), (), step, Some(current_state), |_| next_state);
+        }
+    }
+
+    /// Rotates the field in place by a single bit, the bit wrapping around is carried in the state.
+    fn rotate_once(&mut self, field: Field, left: bool, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        let wrapped_bit = if left { field.width - 1 } else { 0 };
+        let carry_states = [self.allocate_state(), self.allocate_state()];
+        self.walk_fields(
+            &[Field::new(field.get_cell(wrapped_bit), 1)],
+            None,
+            false,
+            |_, _, bits| (bits[0], false),
+            Some(start_state),
+            |bit| carry_states[bit as usize],
+        );
+
+        // Every bit is replaced by the carried one and becomes the next carried bit
+        let swap = |_, carried, bits: &[bool]| (bits[0], carried);
+        for (bit, carry_state) in carry_states.into_iter().enumerate() {
+            if left {
+                self.walk_fields(&[field], Some(field), bit == 1, swap, Some(carry_state), |_| end_state);
+            } else {
+                self.walk_fields_downwards(&[field], Some(field), bit == 1, swap, Some(carry_state), |_| end_state);
+            }
+        }
+
+        (start_state, end_state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::layers::primitive::PrimitiveLayer;
+    use crate::compiler::layers::program_builder::ProgramBuilder;
+    use crate::compiler::turing_compiler::TuringCompiler;
+    use crate::enums::endianness::Endianness;
+    use crate::machine::turing_machine::TuringMachine;
+    use crate::machine::tape::Tape;
+    use crate::machine::turing_tape::TuringTape;
+    use crate::test_utils::get_mask;
+    use super::*;
+
+    /// Cells left of the head are available as well, the head starts at this cell of the tape.
+    const BASE: usize = 32;
+
+    fn build(build_operation: impl FnOnce(&mut TuringCompiler, State, State)) -> TuringCompiler {
+        let mut compiler = TuringCompiler::default();
+        let start_state = compiler.allocate_state();
+        let done = compiler.halt(None);
+        build_operation(&mut compiler, start_state, done);
+        compiler
+    }
+
+    /// Writes the values into their fields, runs the program and reads back the requested fields.
+    fn run(compiler: &TuringCompiler, inputs: &[(Field, u64)], outputs: &[Field]) -> Vec<u64> {
+        let mut tape = TuringTape::default();
+        for (field, value) in inputs {
+            tape.write_unsigned((BASE as isize + field.offset) as usize, *value, field.width, Endianness::Little);
+        }
+        let mut tm = TuringMachine::default().with_program(compiler.get_program()).with_tape(tape);
+        tm.set_head(BASE);
+        tm.run_program();
+
+        assert_eq!(tm.head, BASE);
+        outputs.iter()
+            .map(|field| tm.tape.read_unsigned((BASE as isize + field.offset) as usize, field.width, Endianness::Little))
+            .collect()
+    }
+
+    type BinaryOperation = fn(&mut TuringCompiler, Field, Field, Field, Option<State>, Option<State>) -> (State, State);
+
+    fn test_binary(operation: BinaryOperation, expected: fn(u64, u64) -> u64) {
+        for n in 1..=3 {
+            let width = n as isize;
+            // Separate destination, reversed operands left of the head with a gap, in place,
+            // and destinations shifted by a cell against x or y in either direction
+            let layouts = [
+                (Field::new(0, n), Field::new(width, n), Field::new(2 * width, n)),
+                (Field::new(-width, n), Field::new(-2 * width - 1, n), Field::new(0, n)),
+                (Field::new(0, n), Field::new(width, n), Field::new(width, n)),
+                (Field::new(0, n), Field::new(width + 2, n), Field::new(1, n)),
+                (Field::new(1, n), Field::new(width + 2, n), Field::new(0, n)),
+                (Field::new(-width - 2, n), Field::new(0, n), Field::new(1, n)),
+                (Field::new(-width - 2, n), Field::new(1, n), Field::new(0, n)),
+            ];
+            for (x, y, destination) in layouts {
+                let compiler = build(|compiler, start, done| {
+                    operation(compiler, x, y, destination, Some(start), Some(done));
+                });
+                for a in 0..1 << n {
+                    for b in 0..1 << n {
+                        let mut inputs = vec![(x, a), (y, b)];
+                        if !destination.overlaps(&x) && !destination.overlaps(&y) {
+                            inputs.push((destination, 0));
+                        }
+                        let outputs = run(&compiler, &inputs, &[x, destination]);
+                        assert_eq!(outputs[1], expected(a, b) & get_mask(n), "n={} x={} y={} {:?}", n, a, b, destination);
+                        if !destination.overlaps(&x) {
+                            assert_eq!(outputs[0], a);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_not_n() {
+        for n in 1..=4 {
+            for destination in [Field::new(0, n), Field::new(-(n as isize), n), Field::new(1, n), Field::new(-1, n)] {
+                let x = Field::new(0, n);
+                let compiler = build(|compiler, start, done| {
+                    compiler.not_n(x, destination, Some(start), Some(done));
+                });
+                for a in 0..1 << n {
+                    assert_eq!(run(&compiler, &[(x, a)], &[destination]), vec![!a & get_mask(n)]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_and_n() {
+        test_binary(TuringCompiler::and_n, |a, b| a & b);
+    }
+
+    #[test]
+    fn test_or_n() {
+        test_binary(TuringCompiler::or_n, |a, b| a | b);
+    }
+
+    #[test]
+    fn test_xor_n() {
+        test_binary(TuringCompiler::xor_n, |a, b| a ^ b);
+    }
+
+    #[test]
+    fn test_nand_n() {
+        test_binary(TuringCompiler::nand_n, |a, b| !(a & b));
+    }
+
+    #[test]
+    fn test_nor_n() {
+        test_binary(TuringCompiler::nor_n, |a, b| !(a | b));
+    }
+
+    #[test]
+    #[should_panic(expected = "The destination can't start above one source and below another one it overlaps")]
+    fn test_combine_between_sources() {
+        build(|compiler, start, done| {
+            compiler.and_n(Field::new(0, 3), Field::new(2, 3), Field::new(1, 3), Some(start), Some(done));
+        });
+    }
+
+    type ShiftOperation = fn(&mut TuringCompiler, Field, usize, Field, Option<State>, Option<State>) -> (State, State);
+
+    /// Destinations in place, separate and overlapping the source from either side.
+    fn get_destinations(n: usize) -> Vec<Field> {
+        vec![Field::new(0, n), Field::new(n as isize + 1, n), Field::new(2, n), Field::new(-2, n)]
+    }
+
+    fn test_shift(operation: ShiftOperation, expected: fn(u64, usize) -> u64, destinations: fn(usize) -> Vec<Field>) {
+        for n in 1..=4 {
+            let source = Field::new(0, n);
+            for destination in destinations(n) {
+                for k in 0..=n + 1 {
+                    let compiler = build(|compiler, start, done| {
+                        operation(compiler, source, k, destination, Some(start), Some(done));
+                    });
+                    for a in 0..1 << n {
+                        let outputs = run(&compiler, &[(source, a)], &[destination]);
+                        assert_eq!(outputs, vec![expected(a, k) & get_mask(n)], "n={} a={} k={} {:?}", n, a, k, destination);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_shift_left_n() {
+        test_shift(TuringCompiler::shift_left_n, |a, k| a << k, get_destinations);
+    }
+
+    #[test]
+    fn test_shift_right_n() {
+        test_shift(TuringCompiler::shift_right_n, |a, k| a >> k, get_destinations);
+    }
+
+    /// The cells above the source are set, they must not be shifted in.
+    fn test_shift_wider(operation: ShiftOperation, expected: fn(u64, usize) -> u64) {
+        for n in 1..=4 {
+            let source = Field::new(0, n);
+            let above_source = Field::new(n as isize, 4);
+            let width = n + 4;
+            for destination in [Field::new(0, width), Field::new(n as isize + 1, width), Field::new(-2, width)] {
+                for k in 0..=n + 1 {
+                    let compiler = build(|compiler, start, done| {
+                        operation(compiler, source, k, destination, Some(start), Some(done));
+                    });
+                    for a in 0..1 << n {
+                        let outputs = run(&compiler, &[(above_source, 0b1111), (source, a)], &[destination]);
+                        assert_eq!(outputs, vec![expected(a, k) & get_mask(width)], "n={} a={} k={} {:?}", n, a, k, destination);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_shift_left_n_wider() {
+        test_shift_wider(TuringCompiler::shift_left_n, |a, k| a << k);
+    }
+
+    #[test]
+    fn test_shift_right_n_wider() {
+        test_shift_wider(TuringCompiler::shift_right_n, |a, k| a >> k);
+    }
+
+    fn rotate_left(a: u64, k: usize, n: usize) -> u64 {
+        let k = k % n;
+        ((a << k) | (a >> (n - k))) & get_mask(n)
+    }
+
+    #[test]
+    fn test_rotate_n() {
+        for n in 1..=4 {
+            let source = Field::new(0, n);
+            for destination in [Field::new(0, n), Field::new(-(n as isize) - 1, n)] {
+                for k in 0..=2 * n {
+                    let left = build(|compiler, start, done| {
+                        compiler.rotate_left_n(source, k, destination, Some(start), Some(done));
+                    });
+                    let right = build(|compiler, start, done| {
+                        compiler.rotate_right_n(source, k, destination, Some(start), Some(done));
+                    });
+                    for a in 0..1 << n {
+                        assert_eq!(run(&left, &[(source, a)], &[destination]), vec![rotate_left(a, k, n)]);
+                        assert_eq!(run(&right, &[(source, a)], &[destination]), vec![rotate_left(a, n - k % n, n)]);
+                    }
+                }
+            }
+        }
+    }
+}
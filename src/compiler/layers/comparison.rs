@@ -0,0 +1,170 @@
+use std::cmp::Ordering;
+use crate::compiler::layers::field::FieldLayer;
+use crate::compiler::structures::field::Field;
+use crate::machine::state::State;
+
+/// Compares two n-bit fields in the layout of the [`IntegerLayer`](crate::compiler::layers::integer::IntegerLayer),
+/// `x` under the head followed by `y`. Both fields are left intact and the head returns to the lowest bit of `x`.
+pub trait ComparisonLayer: FieldLayer {
+    /// Compares x to y and transitions to the lt, eq or gt state.
+    /// With `signed` both fields are read as two's complement numbers.
+    fn compare_n(
+        &mut self,
+        n: usize,
+        signed: bool,
+        current_state: Option<State>,
+        lt_state: Option<State>,
+        eq_state: Option<State>,
+        gt_state: Option<State>,
+    ) -> (State, State, State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let lt_state = lt_state.unwrap_or_else(|| self.allocate_state());
+        let eq_state = eq_state.unwrap_or_else(|| self.allocate_state());
+        let gt_state = gt_state.unwrap_or_else(|| self.allocate_state());
+
+        // Going from the lowest to the highest bit, every differing bit overrules the verdict so far
+        self.walk_fields(
+            &[Field::new(0, n), Field::new(n as isize, n)],
+            None,
+            Ordering::Equal,
+            |i, ordering, bits| {
+                let bit_ordering = bits[0].cmp(&bits[1]);
+                let bit_ordering = if signed && i == n - 1 { bit_ordering.reverse() } else { bit_ordering };
+                (bit_ordering.then(ordering), false)
+            },
+            Some(start_state),
+            |ordering| match ordering {
+                Ordering::Less => lt_state,
+                Ordering::Equal => eq_state,
+                Ordering::Greater => gt_state,
+            },
+        );
+
+        (start_state, lt_state, eq_state, gt_state)
+    }
+
+    /// Transitions to the equal state if x equals y, otherwise to the not equal state.
+    fn branch_equal_n(
+        &mut self,
+        n: usize,
+        current_state: Option<State>,
+        equal_state: Option<State>,
+        not_equal_state: Option<State>,
+    ) -> (State, State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let equal_state = equal_state.unwrap_or_else(|| self.allocate_state());
+        let not_equal_state = not_equal_state.unwrap_or_else(|| self.allocate_state());
+
+        self.walk_fields(
+            &[Field::new(0, n), Field::new(n as isize, n)],
+            None,
+            true,
+            |_, equal, bits| (equal && bits[0] == bits[1], false),
+            Some(start_state),
+            |equal| if equal { equal_state } else { not_equal_state },
+        );
+
+        (start_state, equal_state, not_equal_state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::layers::primitive::PrimitiveLayer;
+    use crate::compiler::layers::program_builder::ProgramBuilder;
+    use crate::compiler::turing_compiler::TuringCompiler;
+    use crate::enums::endianness::Endianness;
+    use crate::machine::turing_machine::TuringMachine;
+    use crate::machine::tape::Tape;
+    use crate::machine::turing_tape::TuringTape;
+    use crate::test_utils::XorShift;
+    use super::*;
+
+    /// Every exit marks its own cell after both fields, so the taken exit and the head position can be checked at once.
+    fn build(n: usize, exit_count: usize, build_comparison: impl FnOnce(&mut TuringCompiler, State, &[State])) -> TuringCompiler {
+        let mut compiler = TuringCompiler::default();
+        let start_state = compiler.allocate_state();
+        let exits = compiler.allocate_states(exit_count);
+        for (k, exit) in exits.iter().enumerate() {
+            let (_, mark_state) = compiler.move_by((2 * n + k) as isize, Some(*exit), None);
+            compiler.mark(Some(mark_state), Some(State::halt()));
+        }
+        build_comparison(&mut compiler, start_state, &exits);
+        compiler
+    }
+
+    fn run(compiler: &TuringCompiler, n: usize, x: u64, y: u64, exit_count: usize) -> usize {
+        let mut tape = TuringTape::from_unsigned(x, n, Endianness::Little, 0);
+        tape.write_unsigned(n, y, n, Endianness::Little);
+        let mut tm = TuringMachine::default().with_program(compiler.get_program()).with_tape(tape);
+        tm.run_program();
+
+        assert_eq!(tm.tape.read_unsigned(0, n, Endianness::Little), x);
+        assert_eq!(tm.tape.read_unsigned(n, n, Endianness::Little), y);
+        let marks: Vec<usize> = (0..exit_count).filter(|k| tm.tape.read(2 * n + k)).collect();
+        assert_eq!(marks.len(), 1);
+        marks[0]
+    }
+
+    fn build_compare(n: usize, signed: bool) -> TuringCompiler {
+        build(n, 3, |compiler, start, exits| {
+            compiler.compare_n(n, signed, Some(start), Some(exits[0]), Some(exits[1]), Some(exits[2]));
+        })
+    }
+
+    fn get_exit(ordering: Ordering) -> usize {
+        match ordering {
+            Ordering::Less => 0,
+            Ordering::Equal => 1,
+            Ordering::Greater => 2,
+        }
+    }
+
+    fn to_signed(value: u64, n: usize) -> i64 {
+        ((value << (64 - n)) as i64) >> (64 - n)
+    }
+
+    #[test]
+    fn test_compare_n_exhaustive() {
+        for n in 1..=4 {
+            let unsigned = build_compare(n, false);
+            let signed = build_compare(n, true);
+            for x in 0..1 << n {
+                for y in 0..1 << n {
+                    assert_eq!(run(&unsigned, n, x, y, 3), get_exit(x.cmp(&y)), "unsigned n={} x={} y={}", n, x, y);
+                    let expected = to_signed(x, n).cmp(&to_signed(y, n));
+                    assert_eq!(run(&signed, n, x, y, 3), get_exit(expected), "signed n={} x={} y={}", n, x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_compare_n_random() {
+        let n = 24;
+        let unsigned = build_compare(n, false);
+        let signed = build_compare(n, true);
+        let mut rng = XorShift::new(0xC0FFEE);
+        for _ in 0..30 {
+            let x = rng.next_bits(n);
+            // Close values make the lowest bits decide
+            let y = if rng.next_u64() & 1 == 0 { rng.next_bits(n) } else { x ^ rng.next_bits(3) };
+            assert_eq!(run(&unsigned, n, x, y, 3), get_exit(x.cmp(&y)));
+            assert_eq!(run(&signed, n, x, y, 3), get_exit(to_signed(x, n).cmp(&to_signed(y, n))));
+        }
+    }
+
+    #[test]
+    fn test_branch_equal_n() {
+        for n in 1..=4 {
+            let compiler = build(n, 2, |compiler, start, exits| {
+                compiler.branch_equal_n(n, Some(start), Some(exits[0]), Some(exits[1]));
+            });
+            for x in 0..1 << n {
+                for y in 0..1 << n {
+                    assert_eq!(run(&compiler, n, x, y, 2), (x != y) as usize);
+                }
+            }
+        }
+    }
+}
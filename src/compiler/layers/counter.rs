@@ -0,0 +1,330 @@
+use crate::compiler::layers::field::FieldLayer;
+use crate::compiler::structures::field::Field;
+use crate::machine::state::State;
+
+/// Binary counters stored in a field on the tape, least significant bit first, so loop bounds can come from the tape.
+/// The counter field is relative to the head, which is back where it started when transitioning to the next state.
+pub trait CounterLayer: FieldLayer {
+    /// Sets the counter to the value, truncated to the width of the field.
+    fn init_counter(&mut self, counter: Field, value: u64, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        self.walk_fields(
+            &[],
+            Some(counter),
+            (),
+            |i, _, _| ((), i < 64 && value >> i & 1 == 1),
+            Some(start_state),
+            |_| end_state,
+        );
+
+        (start_state, end_state)
+    }
+
+    /// Adds one to the counter, wrapping around to zero.
+    fn increment_counter(&mut self, counter: Field, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        self.walk_fields(
+            &[counter],
+            Some(counter),
+            true,
+            |_, carry, bits| (bits[0] && carry, bits[0] ^ carry),
+            Some(start_state),
+            |_| end_state,
+        );
+
+        (start_state, end_state)
+    }
+
+    /// Subtracts one from the counter, wrapping around to all ones.
+    fn decrement_counter(&mut self, counter: Field, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        self.walk_fields(
+            &[counter],
+            Some(counter),
+            true,
+            |_, borrow, bits| (!bits[0] && borrow, bits[0] ^ borrow),
+            Some(start_state),
+            |_| end_state,
+        );
+
+        (start_state, end_state)
+    }
+
+    /// Transitions to the zero state if the counter is zero and to the non zero state otherwise.
+    fn branch_counter_zero(
+        &mut self,
+        counter: Field,
+        current_state: Option<State>,
+        zero_state: Option<State>,
+        non_zero_state: Option<State>,
+    ) -> (State, State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let zero_state = zero_state.unwrap_or_else(|| self.allocate_state());
+        let non_zero_state = non_zero_state.unwrap_or_else(|| self.allocate_state());
+
+        self.walk_fields(
+            &[counter],
+            None,
+            false,
+            |_, non_zero, bits| (non_zero || bits[0], false),
+            Some(start_state),
+            |non_zero| if non_zero { non_zero_state } else { zero_state },
+        );
+
+        (start_state, zero_state, non_zero_state)
+    }
+
+    /// Subtracts one from the counter and transitions to the decremented state, unless the counter is zero.
+    /// Then it stays zero and the zero state follows. This takes a single walk over the counter per loop iteration.
+    fn count_down(
+        &mut self,
+        counter: Field,
+        current_state: Option<State>,
+        decremented_state: Option<State>,
+        zero_state: Option<State>,
+    ) -> (State, State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let decremented_state = decremented_state.unwrap_or_else(|| self.allocate_state());
+        let zero_state = zero_state.unwrap_or_else(|| self.allocate_state());
+
+        // A borrow out of the top bit means the counter was zero and wrapped around to all ones
+        let wrapped_state = self.allocate_state();
+        self.walk_fields(
+            &[counter],
+            Some(counter),
+            true,
+            |_, borrow, bits| (!bits[0] && borrow, bits[0] ^ borrow),
+            Some(start_state),
+            |borrow| if borrow { wrapped_state } else { decremented_state },
+        );
+        self.init_counter(counter, 0, Some(wrapped_state), Some(zero_state));
+
+        (start_state, decremented_state, zero_state)
+    }
+
+    /// Runs the body as many times as the counter holds when entering, counting it down to zero.
+    /// The body is built like a [`fragment`](crate::compiler::layers::fragment::FragmentLayer::fragment) and has to
+    /// leave the head where it found it.
+    fn repeat_counter(
+        &mut self,
+        counter: Field,
+        build_body: impl FnOnce(&mut Self, Option<State>, Option<State>) -> (State, State),
+        current_state: Option<State>,
+        next_state: Option<State>,
+    ) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        let (_, body_state, _) = self.count_down(counter, Some(start_state), None, Some(end_state));
+        build_body(self, Some(body_state), Some(start_state));
+
+        (start_state, end_state)
+    }
+
+    /// Runs the body n times, picking the encoding with fewer states. Small loops are unrolled, larger ones count down
+    /// the counter field, which has to be wide enough for n. The body is built like a
+    /// [`fragment`](crate::compiler::layers::fragment::FragmentLayer::fragment) and has to leave the head where it found
+    /// it, since the counter is relative to the head.
+    fn repeat_n(
+        &mut self,
+        n: usize,
+        counter: Field,
+        build_body: impl Fn(&mut Self, Option<State>, Option<State>) -> (State, State),
+        current_state: Option<State>,
+        next_state: Option<State>,
+    ) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        if n == 0 {
+            return self.idle(Some(start_state), Some(end_state));
+        }
+
+        // The first copy is needed by both encodings, it tells the size of the body.
+        // Every further copy starts in the end state of the previous one.
+        let state_count = self.get_state_count();
+        let (body_start, body_end) = build_body(self, None, None);
+        let body_states = self.get_state_count() - state_count;
+
+        if (n - 1) * body_states.saturating_sub(1) <= get_counter_cost(counter) {
+            self.redirect_state(body_start, start_state);
+            let mut state = body_end;
+            for _ in 1..n {
+                (_, state) = build_body(self, Some(state), None);
+            }
+            self.redirect_state(state, end_state);
+        } else {
+            assert!(counter.width >= 64 || n >> counter.width == 0, "The counter is too narrow for {} iterations", n);
+            let (_, count_state) = self.init_counter(counter, n as u64, Some(start_state), None);
+            self.count_down(counter, Some(count_state), Some(body_start), Some(end_state));
+            self.redirect_state(body_end, count_state);
+        }
+
+        (start_state, end_state)
+    }
+}
+
+/// The states added by looping with the counter, built like in [`CounterLayer::repeat_n`]. They don't depend on n.
+/// Every walk over the counter moves out to its lowest bit and back from its highest one, each taking at least a state.
+fn get_counter_cost(counter: Field) -> usize {
+    let top = counter.offset + counter.width as isize - 1;
+    3 * counter.offset.unsigned_abs().max(1) + 4 * top.unsigned_abs().max(1) + 8 * counter.width.saturating_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::layers::primitive::PrimitiveLayer;
+    use crate::compiler::layers::program_builder::ProgramBuilder;
+    use crate::compiler::turing_compiler::TuringCompiler;
+    use crate::enums::endianness::Endianness;
+    use crate::machine::tape::Tape;
+    use crate::machine::turing_machine::TuringMachine;
+    use crate::test_utils::{XorShift, get_mask};
+    use super::*;
+
+    const WIDTH: usize = 5;
+    const HEAD: usize = 8;
+
+    fn run(compiler: &TuringCompiler, values: &[(Field, u64)]) -> TuringMachine {
+        let mut tm = TuringMachine::default().with_program(compiler.get_program());
+        for (field, value) in values {
+            tm.tape.write_unsigned((HEAD as isize + field.offset) as usize, *value, field.width, Endianness::Little);
+        }
+        tm.set_head(HEAD);
+        tm.run_program();
+        assert_eq!(tm.head, HEAD);
+        tm
+    }
+
+    fn read(tm: &TuringMachine, field: Field) -> u64 {
+        tm.tape.read_unsigned((HEAD as isize + field.offset) as usize, field.width, Endianness::Little)
+    }
+
+    #[test]
+    fn test_counter_operations() {
+        let counter = Field::new(1, WIDTH);
+        for value in 0..1 << WIDTH {
+            let mut compiler = TuringCompiler::default();
+            let start_state = compiler.allocate_state();
+            compiler.increment_counter(counter, Some(start_state), Some(State::halt()));
+            assert_eq!(read(&run(&compiler, &[(counter, value)]), counter), (value + 1) & get_mask(WIDTH));
+
+            let mut compiler = TuringCompiler::default();
+            let start_state = compiler.allocate_state();
+            compiler.decrement_counter(counter, Some(start_state), Some(State::halt()));
+            assert_eq!(read(&run(&compiler, &[(counter, value)]), counter), value.wrapping_sub(1) & get_mask(WIDTH));
+
+            let mut compiler = TuringCompiler::default();
+            let start_state = compiler.allocate_state();
+            compiler.init_counter(counter, 0b10110, Some(start_state), Some(State::halt()));
+            assert_eq!(read(&run(&compiler, &[(counter, value)]), counter), 0b10110);
+
+            // The zero exit marks the head cell
+            let mut compiler = TuringCompiler::default();
+            let start_state = compiler.allocate_state();
+            let (_, zero, _) = compiler.branch_counter_zero(counter, Some(start_state), None, Some(State::halt()));
+            compiler.mark(Some(zero), Some(State::halt()));
+            assert_eq!(run(&compiler, &[(counter, value)]).read(), value == 0);
+
+            let mut compiler = TuringCompiler::default();
+            let start_state = compiler.allocate_state();
+            let (_, _, zero) = compiler.count_down(counter, Some(start_state), Some(State::halt()), None);
+            compiler.mark(Some(zero), Some(State::halt()));
+            let tm = run(&compiler, &[(counter, value)]);
+            assert_eq!(tm.read(), value == 0);
+            assert_eq!(read(&tm, counter), value.saturating_sub(1));
+        }
+    }
+
+    #[test]
+    fn test_repeat_counter() {
+        // The loop bound is only known at runtime, the body counts the iterations in a second field
+        let counter = Field::new(0, WIDTH);
+        let iterations = Field::new(WIDTH as isize + 1, WIDTH + 1);
+        let mut compiler = TuringCompiler::default();
+        let start_state = compiler.allocate_state();
+        compiler.repeat_counter(
+            counter,
+            |compiler, start, end| compiler.increment_counter(iterations, start, end),
+            Some(start_state),
+            Some(State::halt()),
+        );
+
+        let mut rng = XorShift::new(47);
+        for _ in 0..10 {
+            let value = rng.next_bits(WIDTH);
+            let tm = run(&compiler, &[(counter, value), (iterations, 0)]);
+            assert_eq!(read(&tm, counter), 0);
+            assert_eq!(read(&tm, iterations), value);
+        }
+    }
+
+    #[test]
+    fn test_counter_cost() {
+        for offset in -8..=8 {
+            for width in 1..=10 {
+                let counter = Field::new(offset, width);
+                for n in [1, 2, 5, (1 << width) - 1] {
+                    let mut compiler = TuringCompiler::default();
+                    let [start_state, body_state, end_state] = [(); 3].map(|_| compiler.allocate_state());
+                    let (_, count_state) = compiler.init_counter(counter, n, Some(start_state), None);
+                    compiler.count_down(counter, Some(count_state), Some(body_state), Some(end_state));
+                    assert_eq!(compiler.get_state_count() - 3, get_counter_cost(counter), "{:?}, n={}", counter, n);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_repeat_n() {
+        let counter = Field::new(0, WIDTH);
+        let iterations = Field::new(WIDTH as isize + 1, WIDTH + 1);
+        let build_body = |compiler: &mut TuringCompiler, start, end| compiler.increment_counter(iterations, start, end);
+        let build = |n| {
+            let mut compiler = TuringCompiler::default();
+            let start_state = compiler.allocate_state();
+            compiler.repeat_n(n, counter, build_body, Some(start_state), Some(State::halt()));
+            compiler
+        };
+
+        // Both encodings built by hand, to check that the cheaper one is picked
+        let mut body = TuringCompiler::default();
+        build_body(&mut body, None, None);
+        let body_states = body.get_state_count();
+        let get_unrolled_states = |n: usize| 1 + n * body_states - n.saturating_sub(1);
+        let get_counter_states = |n: usize| {
+            let mut compiler = TuringCompiler::default();
+            let start_state = compiler.allocate_state();
+            let (_, count_state) = compiler.init_counter(counter, n as u64, Some(start_state), None);
+            let (body_start, body_end) = build_body(&mut compiler, None, None);
+            compiler.count_down(counter, Some(count_state), Some(body_start), Some(State::halt()));
+            compiler.redirect_state(body_end, count_state);
+            compiler.get_state_count()
+        };
+
+        let mut encodings = Vec::new();
+        for n in 0..1 << WIDTH {
+            let compiler = build(n);
+            let tm = run(&compiler, &[(counter, 0), (iterations, 0)]);
+            assert_eq!(read(&tm, iterations), n as u64);
+            assert_eq!(read(&tm, counter), 0);
+
+            if n > 0 {
+                let (unrolled, counted) = (get_unrolled_states(n), get_counter_states(n));
+                assert_eq!(compiler.get_state_count(), unrolled.min(counted), "n={}", n);
+                encodings.push(unrolled <= counted);
+            }
+        }
+
+        // Unrolled up to the crossover point and counted from there on
+        let crossover = encodings.iter().position(|unrolled| !unrolled).unwrap();
+        assert!(crossover > 1);
+        assert!(encodings[crossover..].iter().all(|unrolled| !unrolled));
+    }
+}
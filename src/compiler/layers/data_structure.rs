@@ -0,0 +1,424 @@
+use crate::compiler::layers::field::FieldLayer;
+use crate::compiler::structures::field::Field;
+use crate::enums::movement::Movement;
+use crate::machine::state::State;
+
+/// Tag of a free slot, the first one ends the structure.
+pub const TAG_END: usize = 0b00;
+/// Tag of an entry which was already taken from the front of a queue.
+pub const TAG_DEAD: usize = 0b01;
+/// Tag of the slot holding the register, it marks the start of the structure.
+pub const TAG_BASE: usize = 0b10;
+/// Tag of a slot holding an entry.
+pub const TAG_LIVE: usize = 0b11;
+
+/// A stack or a FIFO queue of w-bit entries on the tape, stored in slots of two tag cells followed by the entry:
+/// ```text
+/// | 0 1 | register (w) | 1 1 | entry (w) | 1 1 | entry (w) | ... | 0 0 |
+///   ^ head
+/// ```
+/// The first slot holds the register, which takes the value to push and receives popped values.
+/// Its tag marks the start of the structure and the first free slot ends it. Tags are numbered with the first cell
+/// as the lowest bit, see the `TAG_` constants. Entries taken from the front of a queue stay behind as dead slots
+/// until the queue is empty again.
+///
+/// Every operation starts and ends with the head on the first cell of the structure. Values are carried one bit at a
+/// time between the register and their slot, so an operation takes O(w²) states and O(w² · len) steps.
+pub trait DataStructureLayer: FieldLayer {
+    /// Writes the tag of an empty structure at the head.
+    fn init_structure(&mut self, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        self.write_slot_tag(TAG_BASE, current_state, next_state)
+    }
+
+    /// Pushes the register onto the stack.
+    fn stack_push(&mut self, w: usize, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        self.append_entry(w, current_state, next_state)
+    }
+
+    /// Pops the top entry of the stack into the register.
+    fn stack_pop(&mut self, w: usize, current_state: Option<State>, success_state: Option<State>, empty_state: Option<State>) -> (State, State, State) {
+        self.take_stack_entry(w, true, current_state, success_state, empty_state)
+    }
+
+    /// Copies the top entry of the stack into the register without removing it.
+    fn stack_peek(&mut self, w: usize, current_state: Option<State>, success_state: Option<State>, empty_state: Option<State>) -> (State, State, State) {
+        self.take_stack_entry(w, false, current_state, success_state, empty_state)
+    }
+
+    fn stack_is_empty(&mut self, w: usize, current_state: Option<State>, empty_state: Option<State>, non_empty_state: Option<State>) -> (State, State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let empty_state = empty_state.unwrap_or_else(|| self.allocate_state());
+        let non_empty_state = non_empty_state.unwrap_or_else(|| self.allocate_state());
+
+        let stride = get_stride(w);
+        let (_, check_state) = self.move_by(stride, Some(start_state), None);
+        let (end_state, _) = self.move_by(-stride, None, Some(empty_state));
+        let (live_state, _) = self.move_by(-stride, None, Some(non_empty_state));
+        self.branch_slot_tag(Some(check_state), [end_state, live_state, live_state, live_state]);
+
+        (start_state, empty_state, non_empty_state)
+    }
+
+    /// Appends the register to the back of the queue.
+    fn queue_push(&mut self, w: usize, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        self.append_entry(w, current_state, next_state)
+    }
+
+    /// Takes the front entry of the queue into the register.
+    fn queue_pop(&mut self, w: usize, current_state: Option<State>, success_state: Option<State>, empty_state: Option<State>) -> (State, State, State) {
+        self.take_queue_entry(w, true, current_state, success_state, empty_state)
+    }
+
+    /// Copies the front entry of the queue into the register without removing it.
+    fn queue_peek(&mut self, w: usize, current_state: Option<State>, success_state: Option<State>, empty_state: Option<State>) -> (State, State, State) {
+        self.take_queue_entry(w, false, current_state, success_state, empty_state)
+    }
+
+    fn queue_is_empty(&mut self, w: usize, current_state: Option<State>, empty_state: Option<State>, non_empty_state: Option<State>) -> (State, State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let empty_state = empty_state.unwrap_or_else(|| self.allocate_state());
+        let non_empty_state = non_empty_state.unwrap_or_else(|| self.allocate_state());
+
+        let (end_state, _) = self.seek_base(w, None, Some(empty_state));
+        let (live_state, _) = self.seek_base(w, None, Some(non_empty_state));
+        self.seek_slot(w, Movement::Right, Some(start_state), [Some(end_state), None, None, Some(live_state)]);
+
+        (start_state, empty_state, non_empty_state)
+    }
+
+    /// Copies the register into the first free slot bit by bit, then tags the slot as live.
+    fn append_entry(&mut self, w: usize, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        let mut state = start_state;
+        for i in 0..w {
+            let next_bit_state = self.allocate_state();
+            for (bit, carry_state) in self.read_slot_bit(i, Some(state)).into_iter().enumerate() {
+                let write_state = self.allocate_state();
+                self.seek_slot(w, Movement::Right, Some(carry_state), [Some(write_state), None, None, None]);
+                let (_, return_state) = self.write_slot_bit(i, bit == 1, Some(write_state), None);
+                self.seek_base(w, Some(return_state), Some(next_bit_state));
+            }
+            state = next_bit_state;
+        }
+
+        let tag_state = self.allocate_state();
+        self.seek_slot(w, Movement::Right, Some(state), [Some(tag_state), None, None, None]);
+        let (_, return_state) = self.write_slot_tag(TAG_LIVE, Some(tag_state), None);
+        self.seek_base(w, Some(return_state), Some(end_state));
+
+        (start_state, end_state)
+    }
+
+    /// Copies the entry before the first free slot into the register bit by bit and clears its slot if requested.
+    fn take_stack_entry(
+        &mut self,
+        w: usize,
+        remove: bool,
+        current_state: Option<State>,
+        success_state: Option<State>,
+        empty_state: Option<State>,
+    ) -> (State, State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let success_state = success_state.unwrap_or_else(|| self.allocate_state());
+        let empty_state = empty_state.unwrap_or_else(|| self.allocate_state());
+
+        let (_, _, mut state) = self.stack_is_empty(w, Some(start_state), Some(empty_state), None);
+        let stride = get_stride(w);
+        for i in 0..w {
+            let next_bit_state = self.allocate_state();
+            let (end_state, top_state) = self.move_by(-stride, None, None);
+            self.seek_slot(w, Movement::Right, Some(state), [Some(end_state), None, None, None]);
+            self.copy_bit_to_register(w, i, Some(top_state), next_bit_state);
+            state = next_bit_state;
+        }
+
+        if remove {
+            let (end_state, top_state) = self.move_by(-stride, None, None);
+            self.seek_slot(w, Movement::Right, Some(state), [Some(end_state), None, None, None]);
+            let (_, return_state) = self.clear_slot(w, Some(top_state), None);
+            self.seek_base(w, Some(return_state), Some(success_state));
+        } else {
+            self.redirect_state(state, success_state);
+        }
+
+        (start_state, success_state, empty_state)
+    }
+
+    /// Copies the first live entry into the register bit by bit and marks its slot as dead if requested.
+    fn take_queue_entry(
+        &mut self,
+        w: usize,
+        remove: bool,
+        current_state: Option<State>,
+        success_state: Option<State>,
+        empty_state: Option<State>,
+    ) -> (State, State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let success_state = success_state.unwrap_or_else(|| self.allocate_state());
+        let empty_state = empty_state.unwrap_or_else(|| self.allocate_state());
+
+        let (_, _, mut state) = self.queue_is_empty(w, Some(start_state), Some(empty_state), None);
+        for i in 0..w {
+            let next_bit_state = self.allocate_state();
+            let front_state = self.allocate_state();
+            self.seek_slot(w, Movement::Right, Some(state), [None, None, None, Some(front_state)]);
+            self.copy_bit_to_register(w, i, Some(front_state), next_bit_state);
+            state = next_bit_state;
+        }
+
+        if !remove {
+            self.redirect_state(state, success_state);
+            return (start_state, success_state, empty_state);
+        }
+
+        let front_state = self.allocate_state();
+        self.seek_slot(w, Movement::Right, Some(state), [None, None, None, Some(front_state)]);
+        let (_, tag_state) = self.clear_slot(w, Some(front_state), None);
+        let (_, check_state) = self.write_slot_tag(TAG_DEAD, Some(tag_state), None);
+
+        // Once the queue is empty again, the dead slots are cleared so it doesn't creep along the tape
+        let stride = get_stride(w);
+        let (_, next_slot_state) = self.move_by(stride, Some(check_state), None);
+        let (return_state, _) = self.seek_base(w, None, Some(success_state));
+        let (keep_state, _) = self.move_by(-stride, None, Some(return_state));
+        let (compact_state, dead_state) = self.move_by(-stride, None, None);
+        self.branch_slot_tag(Some(next_slot_state), [compact_state, keep_state, keep_state, keep_state]);
+
+        let (clear_state, previous_state) = self.clear_slot(w, None, None);
+        self.move_by(-stride, Some(previous_state), Some(dead_state));
+        self.branch_slot_tag(Some(dead_state), [success_state, clear_state, success_state, success_state]);
+
+        (start_state, success_state, empty_state)
+    }
+
+    /// Reads bit i of the slot at the head, which ends up back at the start of the slot.
+    /// Returns the states for a read 0 and 1.
+    fn read_slot_bit(&mut self, i: usize, current_state: Option<State>) -> [State; 2] {
+        let bit_states = [self.allocate_state(), self.allocate_state()];
+        self.walk_fields(
+            &[Field::new(2 + i as isize, 1)],
+            None,
+            false,
+            |_, _, bits| (bits[0], false),
+            current_state,
+            |bit| bit_states[bit as usize],
+        );
+        bit_states
+    }
+
+    fn write_slot_bit(&mut self, i: usize, bit: bool, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        self.walk_fields(&[], Some(Field::new(2 + i as isize, 1)), (), |_, _, _| ((), bit), Some(start_state), |_| end_state);
+
+        (start_state, end_state)
+    }
+
+    /// Copies bit i of the slot at the head into the register and ends on the first cell of the structure.
+    fn copy_bit_to_register(&mut self, w: usize, i: usize, current_state: Option<State>, next_state: State) {
+        for (bit, carry_state) in self.read_slot_bit(i, current_state).into_iter().enumerate() {
+            let (_, write_state) = self.seek_base(w, Some(carry_state), None);
+            self.write_slot_bit(i, bit == 1, Some(write_state), Some(next_state));
+        }
+    }
+
+    /// Transitions to the exit for the tag of the slot at the head.
+    fn branch_slot_tag(&mut self, current_state: Option<State>, exits: [State; 4]) -> State {
+        self.walk_fields(
+            &[Field::new(0, 2)],
+            None,
+            0,
+            |i, tag, bits| (tag | (bits[0] as usize) << i, false),
+            current_state,
+            |tag| exits[tag],
+        )
+    }
+
+    fn write_slot_tag(&mut self, tag: usize, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        self.walk_fields(&[], Some(Field::new(0, 2)), (), |i, _, _| ((), tag >> i & 1 == 1), Some(start_state), |_| end_state);
+
+        (start_state, end_state)
+    }
+
+    /// Clears the tag and the entry of the slot at the head.
+    fn clear_slot(&mut self, w: usize, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        self.walk_fields(&[], Some(Field::new(0, w + 2)), (), |_, _, _| ((), false), Some(start_state), |_| end_state);
+
+        (start_state, end_state)
+    }
+
+    /// Moves slot by slot in the given direction until a slot with a tag that has a stop state is found.
+    /// The slot the head starts on is skipped.
+    fn seek_slot(&mut self, w: usize, movement: Movement, current_state: Option<State>, stops: [Option<State>; 4]) -> State {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+
+        let stride = match movement {
+            Movement::Left => -get_stride(w),
+            _ => get_stride(w),
+        };
+        let (_, check_state) = self.move_by(stride, Some(start_state), None);
+        self.branch_slot_tag(Some(check_state), stops.map(|stop| stop.unwrap_or(start_state)));
+
+        start_state
+    }
+
+    /// Moves left slot by slot until the first cell of the structure.
+    fn seek_base(&mut self, w: usize, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+        let start_state = self.seek_slot(w, Movement::Left, current_state, [None, None, Some(end_state), None]);
+        (start_state, end_state)
+    }
+}
+
+/// The width of a slot, two tag cells and the entry.
+fn get_stride(w: usize) -> isize {
+    (w + 2) as isize
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use crate::compiler::layers::primitive::PrimitiveLayer;
+    use crate::compiler::layers::program_builder::ProgramBuilder;
+    use crate::compiler::turing_compiler::TuringCompiler;
+    use crate::enums::endianness::Endianness;
+    use crate::machine::tape::Tape;
+    use crate::machine::turing_machine::TuringMachine;
+    use crate::machine::turing_program::TuringProgram;
+    use crate::test_utils::XorShift;
+    use super::*;
+
+    /// The structure starts here, the cell before it flags an empty exit.
+    const BASE: usize = 8;
+    const W: usize = 3;
+
+    type Operation = fn(&mut TuringCompiler, usize, Option<State>, Option<State>, Option<State>) -> (State, State, State);
+
+    /// Builds an operation whose empty exit marks the cell before the structure.
+    fn build(operation: impl Fn(&mut TuringCompiler, usize, Option<State>, Option<State>, Option<State>) -> (State, State, State)) -> TuringProgram {
+        let mut compiler = TuringCompiler::default();
+        let start_state = compiler.allocate_state();
+        let done = compiler.halt(None);
+        let (_, _, empty_state) = operation(&mut compiler, W, Some(start_state), Some(done), None);
+        let (_, flag_state) = compiler.move_left(Some(empty_state), None);
+        let (_, return_state) = compiler.mark(Some(flag_state), None);
+        compiler.move_right(Some(return_state), Some(done));
+        compiler.get_program()
+    }
+
+    fn build_push() -> TuringProgram {
+        let mut compiler = TuringCompiler::default();
+        let start_state = compiler.allocate_state();
+        let done = compiler.halt(None);
+        compiler.stack_push(W, Some(start_state), Some(done));
+        compiler.get_program()
+    }
+
+    fn build_is_empty(is_empty: Operation) -> TuringProgram {
+        build(|compiler, w, current, done, empty| {
+            let (start, empty, non_empty) = is_empty(compiler, w, current, empty, None);
+            compiler.redirect_state(non_empty, done.unwrap());
+            (start, done.unwrap(), empty)
+        })
+    }
+
+    fn new_machine() -> TuringMachine {
+        let mut compiler = TuringCompiler::default();
+        let start_state = compiler.allocate_state();
+        let done = compiler.halt(None);
+        compiler.init_structure(Some(start_state), Some(done));
+
+        let mut tm = TuringMachine::default().with_program(compiler.get_program());
+        tm.set_head(BASE);
+        tm.run_program();
+        tm
+    }
+
+    /// Runs the program on the structure and returns the register and whether the empty exit was taken.
+    fn run(tm: &mut TuringMachine, program: &TuringProgram, register: u64) -> (u64, bool) {
+        tm.tape.write_unsigned(BASE + 2, register, W, Endianness::Little);
+        tm.set_program(program.clone());
+        tm.reset_state_but_persist_tape();
+        tm.run_program();
+        assert_eq!(tm.head, BASE);
+
+        let empty = tm.tape.read(BASE - 1);
+        tm.tape.unset(BASE - 1);
+        (tm.tape.read_unsigned(BASE + 2, W, Endianness::Little), empty)
+    }
+
+    #[test]
+    fn test_stack() {
+        let push = build_push();
+        let pop = build(TuringCompiler::stack_pop);
+        let peek = build(TuringCompiler::stack_peek);
+        let is_empty = build_is_empty(TuringCompiler::stack_is_empty);
+
+        let mut rng = XorShift::new(40);
+        let mut tm = new_machine();
+        let mut model = Vec::new();
+        for _ in 0..120 {
+            let value = rng.next_bits(W);
+            match rng.next_u64() % 4 {
+                0 | 1 => {
+                    run(&mut tm, &push, value);
+                    model.push(value);
+                }
+                2 => {
+                    let expected = model.pop();
+                    assert_eq!(run(&mut tm, &pop, value), (expected.unwrap_or(value), expected.is_none()));
+                }
+                _ => {
+                    let expected = model.last().copied();
+                    assert_eq!(run(&mut tm, &peek, value), (expected.unwrap_or(value), expected.is_none()));
+                }
+            }
+            assert_eq!(run(&mut tm, &is_empty, 0).1, model.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_queue() {
+        let push = build_push();
+        let pop = build(TuringCompiler::queue_pop);
+        let peek = build(TuringCompiler::queue_peek);
+        let is_empty = build_is_empty(TuringCompiler::queue_is_empty);
+
+        let mut rng = XorShift::new(41);
+        let mut tm = new_machine();
+        let mut model = VecDeque::new();
+        for _ in 0..120 {
+            let value = rng.next_bits(W);
+            match rng.next_u64() % 4 {
+                0 => {
+                    run(&mut tm, &push, value);
+                    model.push_back(value);
+                }
+                1 | 2 => {
+                    let expected = model.pop_front();
+                    assert_eq!(run(&mut tm, &pop, value), (expected.unwrap_or(value), expected.is_none()));
+                }
+                _ => {
+                    let expected = model.front().copied();
+                    assert_eq!(run(&mut tm, &peek, value), (expected.unwrap_or(value), expected.is_none()));
+                }
+            }
+            assert_eq!(run(&mut tm, &is_empty, 0).1, model.is_empty());
+        }
+
+        // Dead slots are cleared once the queue runs empty
+        while model.pop_front().is_some() {
+            run(&mut tm, &pop, 0);
+        }
+        assert_eq!(tm.tape.read_bit_string(BASE, 40), format!("01{}", "0".repeat(38)));
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use crate::compiler::layers::base::BaseLayer;
+use crate::compiler::structures::field::Field;
+use crate::enums::movement::Movement;
+use crate::machine::instruction::Instruction;
+use crate::machine::state::State;
+
+/// Operations on whole fields of the tape, whose offsets are known at compile time.
+pub trait FieldLayer: BaseLayer {
+    /// The current state will move the head by the given (possibly negative or zero) number of cells.
+    fn move_by(&mut self, delta: isize, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        match delta {
+            0 => self.idle(current_state, next_state),
+            delta if delta > 0 => self.move_right_x(delta as usize, current_state, next_state),
+            delta => self.move_left_x(delta.unsigned_abs(), current_state, next_state),
+        }
+    }
+
+    /// Walks over the fields bit by bit, starting at the least significant bit.
+    ///
+    /// For every bit position `i` the head visits the sources which are at least `i + 1` bits wide, then the destination.
+    /// `step` receives the position, the carried value and the bits read from the sources, and returns the new carried value
+    /// and the bit to write into the destination. The carried value has to come from a small finite set, since every
+    /// distinct value is encoded as its own set of states.
+    ///
+    /// The head returns to where it started and transitions to the exit state chosen for the final carried value.
+    fn walk_fields<S: Copy + Eq + Hash>(
+        &mut self,
+        sources: &[Field],
+        destination: Option<Field>,
+        initial: S,
+        step: impl Fn(usize, S, &[bool]) -> (S, bool),
+        current_state: Option<State>,
+        exit_state: impl Fn(S) -> State,
+    ) -> State {
+        walk(self, sources, destination, false, initial, step, current_state, exit_state)
+    }
+
+    /// Like [`walk_fields`](FieldLayer::walk_fields) but starting at the most significant bit.
+    /// Needed when the destination overlaps a source which is read at lower offsets.
+    fn walk_fields_downwards<S: Copy + Eq + Hash>(
+        &mut self,
+        sources: &[Field],
+        destination: Option<Field>,
+        initial: S,
+        step: impl Fn(usize, S, &[bool]) -> (S, bool),
+        current_state: Option<State>,
+        exit_state: impl Fn(S) -> State,
+    ) -> State {
+        walk(self, sources, destination, true, initial, step, current_state, exit_state)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn walk<C: FieldLayer + ?Sized, S: Copy + Eq + Hash>(
+    compiler: &mut C,
+    sources: &[Field],
+    destination: Option<Field>,
+    downwards: bool,
+    initial: S,
+    step: impl Fn(usize, S, &[bool]) -> (S, bool),
+    current_state: Option<State>,
+    exit_state: impl Fn(S) -> State,
+) -> State {
+    let start_state = current_state.unwrap_or_else(|| compiler.allocate_state());
+
+    let mut walker = FieldWalker {
+        sources,
+        destination,
+        width: sources.iter().chain(destination.iter()).map(|field| field.width).max().unwrap_or(0),
+        downwards,
+        step,
+        exit_state,
+        nodes: HashMap::new(),
+        chains: HashMap::new(),
+    };
+    let (first_state, first_position) = walker.resolve(compiler, 0, 0, initial, Vec::new());
+    compiler.move_by(first_position, Some(start_state), Some(first_state));
+
+    start_state
+}
+
+/// The cell visited by the walk, a source to read from or the destination to write to.
+#[derive(Clone, Copy)]
+enum Visit {
+    Read(isize),
+    Write(isize),
+}
+
+/// A point of the walk: walk index, visit index, carried value and the bits read so far at this position.
+type WalkPoint<S> = (usize, usize, S, Vec<bool>);
+
+struct FieldWalker<'a, S, F, E> {
+    sources: &'a [Field],
+    destination: Option<Field>,
+    width: usize,
+    downwards: bool,
+    step: F,
+    exit_state: E,
+    /// Every distinct point of the walk gets one state, stored with the offset it's entered at
+    nodes: HashMap<WalkPoint<S>, (State, isize)>,
+    /// Movement chains towards a target state, shared by all edges with the same distance and target
+    chains: HashMap<(isize, State), State>,
+}
+
+impl<S, F, E> FieldWalker<'_, S, F, E>
+where
+    S: Copy + Eq + Hash,
+    F: Fn(usize, S, &[bool]) -> (S, bool),
+    E: Fn(S) -> State,
+{
+    /// The bit position visited at the given index of the walk.
+    fn get_bit(&self, p: usize) -> usize {
+        if self.downwards { self.width - 1 - p } else { p }
+    }
+
+    fn get_visits(&self, i: usize) -> Vec<Visit> {
+        let mut visits: Vec<Visit> = self.sources.iter()
+            .filter(|field| i < field.width)
+            .map(|field| Visit::Read(field.get_cell(i)))
+            .collect();
+        if let Some(destination) = self.destination.filter(|field| i < field.width) {
+            visits.push(Visit::Write(destination.get_cell(i)));
+        }
+        visits
+    }
+
+    /// Reads of sources narrower than the position are implicitly zero.
+    fn get_source_bits(&self, i: usize, read_bits: &[bool]) -> Vec<bool> {
+        let mut read_bits = read_bits.iter();
+        self.sources.iter()
+            .map(|field| i < field.width && *read_bits.next().unwrap())
+            .collect()
+    }
+
+    /// Returns the state for the given point of the walk and the offset the head has to be at when entering it.
+    fn resolve<C: BaseLayer + ?Sized>(&mut self, compiler: &mut C, p: usize, v: usize, carried: S, read_bits: Vec<bool>) -> (State, isize) {
+        if p == self.width {
+            return ((self.exit_state)(carried), 0);
+        }
+
+        let i = self.get_bit(p);
+        let visits = self.get_visits(i);
+        if v == visits.len() {
+            // Nothing to write at this position, the step happens without visiting a cell
+            let (carried, _) = (self.step)(i, carried, &self.get_source_bits(i, &read_bits));
+            return self.resolve(compiler, p + 1, 0, carried, Vec::new());
+        }
+
+        let key = (p, v, carried, read_bits);
+        if let Some(node) = self.nodes.get(&key) {
+            return *node;
+        }
+        let (_, _, _, read_bits) = key.clone();
+
+        let state = compiler.allocate_state();
+        let (position, is_write) = match visits[v] {
+            Visit::Read(position) => (position, false),
+            Visit::Write(position) => (position, true),
+        };
+        self.nodes.insert(key, (state, position));
+
+        if is_write {
+            let (carried, write_bit) = (self.step)(i, carried, &self.get_source_bits(i, &read_bits));
+            let (target, target_position) = self.resolve(compiler, p + 1, 0, carried, Vec::new());
+            for read_bit in [false, true] {
+                self.add_edge(compiler, state, read_bit, write_bit, target_position - position, target);
+            }
+        } else {
+            for read_bit in [false, true] {
+                let mut next_bits = read_bits.clone();
+                next_bits.push(read_bit);
+                let (target, target_position) = self.resolve(compiler, p, v + 1, carried, next_bits);
+                self.add_edge(compiler, state, read_bit, read_bit, target_position - position, target);
+            }
+        }
+
+        (state, position)
+    }
+
+    /// Adds the instruction for one read bit, which moves the head by delta cells on its way to the target.
+    fn add_edge<C: BaseLayer + ?Sized>(&mut self, compiler: &mut C, state: State, read_bit: bool, write_bit: bool, delta: isize, target: State) {
+        let (movement, remaining) = match delta {
+            0 => (Movement::Stay, 0),
+            delta if delta > 0 => (Movement::Right, delta - 1),
+            delta => (Movement::Left, delta + 1),
+        };
+
+        let next_state = if remaining == 0 {
+            target
+        } else if let Some(chain) = self.chains.get(&(remaining, target)) {
+            *chain
+        } else {
+            let (chain, _) = match remaining {
+                remaining if remaining > 0 => compiler.move_right_x(remaining as usize, None, Some(target)),
+                remaining => compiler.move_left_x(remaining.unsigned_abs(), None, Some(target)),
+            };
+            self.chains.insert((remaining, target), chain);
+            chain
+        };
+
+        compiler.add_instruction(
+            Instruction::new(state, read_bit, write_bit)
+                .with_movement(movement)
+                .with_next_state(next_state)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::layers::primitive::PrimitiveLayer;
+    use crate::compiler::layers::program_builder::ProgramBuilder;
+    use crate::compiler::turing_compiler::TuringCompiler;
+    use crate::machine::turing_machine::TuringMachine;
+    use crate::machine::tape::Tape;
+    use crate::machine::turing_tape::TuringTape;
+    use super::*;
+
+    fn run(compiler: &TuringCompiler, tape: &str, head: usize) -> TuringMachine {
+        let mut tm = TuringMachine::default()
+            .with_program(compiler.get_program())
+            .with_tape(TuringTape::from_bit_string(tape));
+        tm.set_head(head);
+        tm.run_program();
+        tm
+    }
+
+    #[test]
+    fn test_move_by() {
+        for delta in [-3, 0, 2] {
+            let mut compiler = TuringCompiler::default();
+            let move_state = compiler.allocate_state();
+            let done = compiler.halt(None);
+            compiler.move_by(delta, Some(move_state), Some(done));
+
+            let tm = run(&compiler, "0", 0);
+            assert_eq!(tm.get_position(), delta as i64);
+        }
+    }
+
+    #[test]
+    fn test_walk_fields_copy() {
+        // Copies the field left of the head into the field right of it, the head returns to the start
+        let mut compiler = TuringCompiler::default();
+        let walk_state = compiler.allocate_state();
+        let done = compiler.halt(None);
+        compiler.walk_fields(
+            &[Field::new(-4, 4)],
+            Some(Field::new(1, 4)),
+            (),
+            |_, carried, bits| (carried, bits[0]),
+            Some(walk_state),
+            |_| done,
+        );
+
+        let tm = run(&compiler, "1101_0_0000", 4);
+        assert_eq!(tm.head, 4);
+        assert_eq!(tm.tape.read_bit_string(0, 9), "110101101");
+    }
+
+    #[test]
+    fn test_walk_fields_downwards() {
+        // Shifts a field up by one in place, which only works from the top
+        let mut compiler = TuringCompiler::default();
+        let walk_state = compiler.allocate_state();
+        let done = compiler.halt(None);
+        compiler.walk_fields_downwards(
+            &[Field::new(-1, 5)],
+            Some(Field::new(0, 5)),
+            (),
+            |i, carried, bits| (carried, i > 0 && bits[0]),
+            Some(walk_state),
+            |_| done,
+        );
+
+        let tm = run(&compiler, "1_10110", 1);
+        assert_eq!(tm.head, 1);
+        assert_eq!(tm.tape.read_bit_string(0, 6), "101011");
+    }
+
+    #[test]
+    fn test_walk_fields_exits() {
+        // Counts the ones of a field modulo 3 without writing anything
+        for (tape, expected) in [("0000", 0), ("1011", 0), ("0110", 2), ("1000", 1)] {
+            let mut compiler = TuringCompiler::default();
+            let walk_state = compiler.allocate_state();
+            let exits = compiler.allocate_states(3);
+            compiler.walk_fields(
+                &[Field::new(0, 4)],
+                None,
+                0usize,
+                |_, count, bits| ((count + bits[0] as usize) % 3, false),
+                Some(walk_state),
+                |count| exits[count],
+            );
+            for (count, exit) in exits.iter().enumerate() {
+                compiler.write_and_move(count == expected, Movement::Stay, Some(*exit), Some(State::halt()));
+            }
+
+            let tm = run(&compiler, tape, 0);
+            assert_eq!(tm.head, 0);
+            assert!(tm.read());
+        }
+    }
+
+    #[test]
+    fn test_walk_fields_narrow_sources() {
+        // Adds a 2 bit field onto a 4 bit field, missing bits of the narrow field read as zero
+        let mut compiler = TuringCompiler::default();
+        let walk_state = compiler.allocate_state();
+        let done = compiler.halt(None);
+        let y = Field::new(2, 4);
+        compiler.walk_fields(
+            &[Field::new(0, 2), y],
+            Some(y),
+            false,
+            |_, carry, bits| {
+                let sum = bits[0] as u8 + bits[1] as u8 + carry as u8;
+                (sum > 1, sum % 2 == 1)
+            },
+            Some(walk_state),
+            |_| done,
+        );
+
+        // 3 + 13 = 16, which wraps around to 0
+        let tm = run(&compiler, "11_1011", 0);
+        assert_eq!(tm.tape.read_bit_string(0, 6), "110000");
+
+        // 1 + 5 = 6
+        let tm = run(&compiler, "10_1010", 0);
+        assert_eq!(tm.tape.read_bit_string(0, 6), "100110");
+    }
+}
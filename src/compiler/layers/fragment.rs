@@ -0,0 +1,248 @@
+use crate::compiler::layers::pattern::PatternLayer;
+use crate::compiler::structures::fragment::Fragment;
+use crate::compiler::structures::pattern::Pattern;
+use crate::enums::movement::Movement;
+use crate::machine::state::State;
+use crate::machine::turing_program::TuringProgram;
+
+/// Structured control flow on top of the lower layers.
+/// Fragments are built first and wired together afterwards, so no states have to be threaded through by hand.
+pub trait FragmentLayer: PatternLayer {
+    /// Turns the fragment into the whole program, it's entered from `State(0)` and halts at its exit.
+    /// Fragments built before this call may refer to renumbered states afterwards.
+    ///
+    /// # Panics
+    /// If the exit already has instructions, it has to be a state which is only transitioned into.
+    fn finish(&mut self, program: Fragment) {
+        assert!(
+            self.get_instruction(program.exit, false).is_none() && self.get_instruction(program.exit, true).is_none(),
+            "The exit of the program can't have instructions of its own"
+        );
+        let halt_state = self.get_halt_state();
+        self.redirect_state(program.exit, halt_state);
+        self.swap_states(program.entry, State::new(0));
+    }
+
+    /// Wraps any layer method into a fragment.
+    ///
+    /// # Examples
+    /// ```ignore
+    /// let step = compiler.fragment(|compiler, start, end| compiler.move_right_x(3, start, end));
+    /// ```
+    fn fragment(&mut self, build: impl FnOnce(&mut Self, Option<State>, Option<State>) -> (State, State)) -> Fragment {
+        build(self, None, None).into()
+    }
+
+    /// Embeds a prebuilt program, see [`ProgramBuilder::import_program`](crate::compiler::layers::program_builder::ProgramBuilder::import_program).
+    fn import(&mut self, program: &TuringProgram) -> Fragment {
+        self.fragment(|compiler, start, end| compiler.import_program(program, start, end))
+    }
+
+    /// A fragment which does nothing but take a single step.
+    fn pass(&mut self) -> Fragment {
+        self.fragment(|compiler, start, end| compiler.idle(start, end))
+    }
+
+    /// Runs the fragments one after another.
+    fn seq(&mut self, fragments: &[Fragment]) -> Fragment {
+        let Some((first, rest)) = fragments.split_first() else {
+            return self.pass();
+        };
+
+        let mut exit = first.exit;
+        for fragment in rest {
+            self.redirect_state(exit, fragment.entry);
+            exit = fragment.exit;
+        }
+
+        Fragment::new(first.entry, exit)
+    }
+
+    /// Runs `then` if the current bit equals the target bit, otherwise runs `otherwise`.
+    fn if_bit(&mut self, target_bit: bool, then: Fragment, otherwise: Fragment) -> Fragment {
+        let (entry, _, _) = self.branch_when(
+            target_bit,
+            Movement::Stay,
+            Movement::Stay,
+            None,
+            Some(then.entry),
+            Some(otherwise.entry)
+        );
+        self.redirect_state(otherwise.exit, then.exit);
+
+        Fragment::new(entry, then.exit)
+    }
+
+    /// Runs the body as long as the current bit equals the target bit, the bit is checked before every iteration.
+    fn while_bit(&mut self, target_bit: bool, body: Fragment) -> Fragment {
+        let (entry, _, exit) = self.branch_when(
+            target_bit,
+            Movement::Stay,
+            Movement::Stay,
+            None,
+            Some(body.entry),
+            None
+        );
+        self.redirect_state(body.exit, entry);
+
+        Fragment::new(entry, exit)
+    }
+
+    /// Builds the body n times and runs the copies one after another.
+    fn repeat(&mut self, n: usize, build_body: impl Fn(&mut Self) -> Fragment) -> Fragment {
+        let fragments: Vec<Fragment> = (0..n).map(|_| build_body(self)).collect();
+        self.seq(&fragments)
+    }
+
+    /// Runs the body until the pattern starts under the head, read in the given direction.
+    /// The pattern is checked before every iteration, so the body has to move the head or change the tape to terminate.
+    fn loop_until_pattern(&mut self, pattern: Pattern, movement: Movement, body: Fragment) -> Fragment {
+        let (entry, exit, _) = self.match_pattern(pattern, movement, None, None, Some(body.entry));
+        self.redirect_state(body.exit, entry);
+
+        Fragment::new(entry, exit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::layers::base::BaseLayer;
+    use crate::compiler::layers::primitive::PrimitiveLayer;
+    use crate::compiler::layers::program_builder::ProgramBuilder;
+    use crate::compiler::turing_compiler::TuringCompiler;
+    use crate::machine::turing_machine::TuringMachine;
+    use crate::machine::tape::Tape;
+    use crate::machine::turing_tape::TuringTape;
+    use super::*;
+
+    fn run(compiler: &mut TuringCompiler, program: Fragment, tape: &str) -> TuringMachine {
+        compiler.finish(program);
+
+        let mut tm = TuringMachine::default()
+            .with_program(compiler.get_program())
+            .with_tape(TuringTape::from_bit_string(tape));
+        tm.run_program();
+        tm
+    }
+
+    #[test]
+    fn test_seq() {
+        let mut compiler = TuringCompiler::default();
+        let mark = compiler.fragment(|compiler, start, end| compiler.mark_and_move_right(start, end));
+        let skip = compiler.fragment(|compiler, start, end| compiler.move_right_x(2, start, end));
+        let unmark = compiler.fragment(|compiler, start, end| compiler.unmark(start, end));
+        let program = compiler.seq(&[mark, skip, unmark]);
+
+        let tm = run(&mut compiler, program, "0111_1");
+        assert_eq!(tm.tape.read_bit_string(0, 5), "11101");
+        assert_eq!(tm.head, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "The exit of the program can't have instructions of its own")]
+    fn test_finish_with_exit_instructions() {
+        let mut compiler = TuringCompiler::default();
+        let mark = compiler.fragment(|compiler, start, end| compiler.mark_and_move_right(start, end));
+        let step = compiler.fragment(|compiler, start, end| compiler.move_right(start, end));
+        compiler.finish(Fragment::new(mark.entry, step.entry));
+    }
+
+    #[test]
+    fn test_if_bit() {
+        for (tape, expected) in [("1", "10"), ("0", "01")] {
+            let mut compiler = TuringCompiler::default();
+            let then = compiler.fragment(|compiler, start, end| compiler.unmark(start, end));
+            let otherwise = compiler.fragment(|compiler, start, end| compiler.move_right(start, end));
+            let write = compiler.fragment(|compiler, start, end| compiler.mark(start, end));
+            let branch = compiler.if_bit(true, then, otherwise);
+            let program = compiler.seq(&[branch, write]);
+
+            let tm = run(&mut compiler, program, tape);
+            assert_eq!(tm.tape.read_bit_string(0, 2), expected);
+        }
+    }
+
+    #[test]
+    fn test_while_bit() {
+        let mut compiler = TuringCompiler::default();
+        let body = compiler.fragment(|compiler, start, end| compiler.unmark_and_move_right(start, end));
+        let program = compiler.while_bit(true, body);
+
+        let tm = run(&mut compiler, program, "1110_1");
+        assert_eq!(tm.tape.read_bit_string(0, 5), "00001");
+        assert_eq!(tm.head, 3);
+
+        // The body is skipped entirely if the bit doesn't match
+        let mut compiler = TuringCompiler::default();
+        let body = compiler.fragment(|compiler, start, end| compiler.unmark_and_move_right(start, end));
+        let program = compiler.while_bit(true, body);
+
+        let tm = run(&mut compiler, program, "0110_1");
+        assert_eq!(tm.tape.read_bit_string(0, 5), "01101");
+        assert_eq!(tm.head, 0);
+    }
+
+    #[test]
+    fn test_repeat() {
+        let mut compiler = TuringCompiler::default();
+        let program = compiler.repeat(3, |compiler| {
+            compiler.fragment(|compiler, start, end| compiler.write_and_move(true, Movement::Right, start, end))
+        });
+        let tm = run(&mut compiler, program, "0");
+        assert_eq!(tm.tape.read_bit_string(0, 4), "1110");
+        assert_eq!(tm.head, 3);
+
+        let mut compiler = TuringCompiler::default();
+        let program = compiler.repeat(0, |compiler| {
+            compiler.fragment(|compiler, start, end| compiler.mark(start, end))
+        });
+        let tm = run(&mut compiler, program, "0");
+        assert_eq!(tm.tape.read_bit_string(0, 1), "0");
+    }
+
+    #[test]
+    fn test_loop_until_pattern() {
+        let mut compiler = TuringCompiler::default();
+        let step = compiler.fragment(|compiler, start, end| compiler.move_right(start, end));
+        let program = compiler.loop_until_pattern(Pattern::new(vec![true, true, false]), Movement::Right, step);
+
+        let tm = run(&mut compiler, program, "0101_1101_10");
+        assert_eq!(tm.head, 4);
+        assert_eq!(tm.tape.read_bit_string(0, 10), "0101110110");
+    }
+
+    #[test]
+    fn test_import() {
+        let mut compiler = TuringCompiler::default();
+        let scan_state = compiler.allocate_state();
+        let done = compiler.halt(None);
+        compiler.scan_single(true, Movement::Right, Movement::Stay, Some(scan_state), Some(done));
+        let scan = compiler.get_program();
+
+        // Scan to the first mark twice, clearing it in between
+        let mut compiler = TuringCompiler::default();
+        let first_scan = compiler.import(&scan);
+        let clear = compiler.fragment(|compiler, start, end| compiler.unmark(start, end));
+        let second_scan = compiler.import(&scan);
+        let program = compiler.seq(&[first_scan, clear, second_scan]);
+
+        let tm = run(&mut compiler, program, "0010_01");
+        assert_eq!(tm.tape.read_bit_string(0, 6), "000001");
+        assert_eq!(tm.head, 5);
+    }
+
+    #[test]
+    fn test_nested() {
+        // Clear every run of ones, skipping the zeros in between, until two zeros in a row are found
+        let mut compiler = TuringCompiler::default();
+        let clear = compiler.fragment(|compiler, start, end| compiler.unmark_and_move_right(start, end));
+        let clear_run = compiler.while_bit(true, clear);
+        let skip = compiler.fragment(|compiler, start, end| compiler.move_right(start, end));
+        let body = compiler.seq(&[skip, clear_run]);
+        let program = compiler.loop_until_pattern(Pattern::new(vec![false, false]), Movement::Right, body);
+
+        let tm = run(&mut compiler, program, "0110_1011_1001_1");
+        assert_eq!(tm.tape.read_bit_string(0, 14), "00000000000110");
+        assert_eq!(tm.head, 9);
+    }
+}
@@ -0,0 +1,398 @@
+use crate::compiler::layers::field::FieldLayer;
+use crate::compiler::structures::field::Field;
+use crate::machine::state::State;
+
+/// Wrapping arithmetic on n-bit unsigned (or two's complement) integers.
+///
+/// Binary operations expect two separate fields next to each other, both least significant bit first,
+/// with the head on the lowest bit of `x`. The result replaces `y`, `x` is left intact:
+/// ```text
+/// | x_0 x_1 ... x_n-1 | y_0 y_1 ... y_n-1 |
+///   ^ head
+/// ```
+/// Unary operations work in place on the field starting under the head.
+/// The head is back on the lowest bit of the first field when transitioning to the next state.
+pub trait IntegerLayer: FieldLayer {
+    /// y := x + y
+    fn add_n(&mut self, n: usize, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        let y = Field::new(n as isize, n);
+        self.walk_fields(
+            &[Field::new(0, n), y],
+            Some(y),
+            false,
+            full_add,
+            Some(start_state),
+            |_| end_state,
+        );
+
+        (start_state, end_state)
+    }
+
+    /// y := y - x
+    fn sub_n(&mut self, n: usize, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        let y = Field::new(n as isize, n);
+        self.walk_fields(
+            &[Field::new(0, n), y],
+            Some(y),
+            false,
+            full_sub,
+            Some(start_state),
+            |_| end_state,
+        );
+
+        (start_state, end_state)
+    }
+
+    /// x := x + 1
+    fn increment_n(&mut self, n: usize, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        let x = Field::new(0, n);
+        self.walk_fields(
+            &[x],
+            Some(x),
+            true,
+            |_, carry, bits| (bits[0] && carry, bits[0] ^ carry),
+            Some(start_state),
+            |_| end_state,
+        );
+
+        (start_state, end_state)
+    }
+
+    /// x := x - 1
+    fn decrement_n(&mut self, n: usize, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        let x = Field::new(0, n);
+        self.walk_fields(
+            &[x],
+            Some(x),
+            true,
+            |_, borrow, bits| (!bits[0] && borrow, bits[0] ^ borrow),
+            Some(start_state),
+            |_| end_state,
+        );
+
+        (start_state, end_state)
+    }
+
+    /// x := -x, the two's complement of x
+    fn negate_n(&mut self, n: usize, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        // Inverting all bits and adding one
+        let x = Field::new(0, n);
+        self.walk_fields(
+            &[x],
+            Some(x),
+            true,
+            |_, carry, bits| (!bits[0] && carry, !bits[0] ^ carry),
+            Some(start_state),
+            |_| end_state,
+        );
+
+        (start_state, end_state)
+    }
+
+    /// p := x * y by shifting and adding, `p` is a 2n-bit field right after `y` which is cleared first:
+    /// ```text
+    /// | x (n) | y (n) | p (2n) |
+    /// ```
+    /// For every set bit `y_j`, `x` is added onto the n + 1 bits of `p` starting at `p_j`.
+    /// All n additions are unrolled, so it takes O(n³) states and O(n³) steps.
+    fn multiply_n(&mut self, n: usize, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        let mut state = if n == 0 { end_state } else { self.allocate_state() };
+        self.walk_fields(
+            &[],
+            Some(Field::new(2 * n as isize, 2 * n)),
+            (),
+            |_, _, _| ((), false),
+            Some(start_state),
+            |_| state,
+        );
+
+        for j in 0..n {
+            let add_state = self.allocate_state();
+            let next_iteration = if j == n - 1 { end_state } else { self.allocate_state() };
+
+            self.walk_fields(
+                &[Field::new((n + j) as isize, 1)],
+                None,
+                false,
+                |_, _, bits| (bits[0], false),
+                Some(state),
+                |bit| if bit { add_state } else { next_iteration },
+            );
+            let partial_product = Field::new((2 * n + j) as isize, n + 1);
+            self.walk_fields(
+                &[Field::new(0, n), partial_product],
+                Some(partial_product),
+                false,
+                full_add,
+                Some(add_state),
+                |_| next_iteration,
+            );
+
+            state = next_iteration;
+        }
+
+        (start_state, end_state)
+    }
+
+    /// Restoring division of `a` by `d`, the cells after `a` have to be blank and receive the quotient:
+    /// ```text
+    /// | d (n) | a (n) | 0 (n) |  =>  | d (n) | r (n) | q (n) |
+    /// ```
+    /// The n + 1 bits at `a_i` form the partial remainder of step i, counting down from n - 1.
+    /// `d` is subtracted from it and added back if that borrowed, otherwise its highest bit (which is free now) becomes `q_i`.
+    /// Dividing by zero results in a quotient with all bits set and the dividend as remainder.
+    /// It takes O(n³) states and O(n³) steps.
+    fn divide_n(&mut self, n: usize, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        if n == 0 {
+            return self.idle(Some(start_state), Some(end_state));
+        }
+
+        let divisor = Field::new(0, n);
+        let mut state = start_state;
+        for i in (0..n).rev() {
+            let restore_state = self.allocate_state();
+            let quotient_state = self.allocate_state();
+            let next_iteration = if i == 0 { end_state } else { self.allocate_state() };
+
+            let partial_remainder = Field::new((n + i) as isize, n + 1);
+            self.walk_fields(
+                &[divisor, partial_remainder],
+                Some(partial_remainder),
+                false,
+                full_sub,
+                Some(state),
+                |borrow| if borrow { restore_state } else { quotient_state },
+            );
+            self.walk_fields(
+                &[divisor, partial_remainder],
+                Some(partial_remainder),
+                false,
+                full_add,
+                Some(restore_state),
+                |_| next_iteration,
+            );
+            self.walk_fields(
+                &[],
+                Some(Field::new((2 * n + i) as isize, 1)),
+                (),
+                |_, _, _| ((), true),
+                Some(quotient_state),
+                |_| next_iteration,
+            );
+
+            state = next_iteration;
+        }
+
+        (start_state, end_state)
+    }
+}
+
+/// One bit of x + y, with the sources in the order x, y.
+fn full_add(_: usize, carry: bool, bits: &[bool]) -> (bool, bool) {
+    let sum = bits[0] as u8 + bits[1] as u8 + carry as u8;
+    (sum > 1, sum & 1 == 1)
+}
+
+/// One bit of y - x, with the sources in the order x, y.
+fn full_sub(_: usize, borrow: bool, bits: &[bool]) -> (bool, bool) {
+    let difference = bits[1] as i8 - bits[0] as i8 - borrow as i8;
+    (difference < 0, difference & 1 == 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::layers::primitive::PrimitiveLayer;
+    use crate::compiler::layers::program_builder::ProgramBuilder;
+    use crate::compiler::turing_compiler::TuringCompiler;
+    use crate::enums::endianness::Endianness;
+    use crate::machine::turing_machine::TuringMachine;
+    use crate::machine::tape::Tape;
+    use crate::machine::turing_tape::TuringTape;
+    use crate::test_utils::{get_mask, XorShift};
+    use super::*;
+
+    const WIDTHS: [usize; 5] = [1, 3, 8, 17, 64];
+
+    type Operation = fn(&mut TuringCompiler, usize, Option<State>, Option<State>) -> (State, State);
+
+    fn build(operation: Operation, n: usize) -> TuringCompiler {
+        let mut compiler = TuringCompiler::default();
+        let start_state = compiler.allocate_state();
+        let done = compiler.halt(None);
+        operation(&mut compiler, n, Some(start_state), Some(done));
+        compiler
+    }
+
+    fn run_binary(compiler: &TuringCompiler, n: usize, x: u64, y: u64) -> u64 {
+        let mut tape = TuringTape::from_unsigned(x, n, Endianness::Little, 0);
+        tape.write_unsigned(n, y, n, Endianness::Little);
+        let mut tm = TuringMachine::default().with_program(compiler.get_program()).with_tape(tape);
+        tm.run_program();
+
+        assert_eq!(tm.get_position(), 0);
+        assert_eq!(tm.tape.read_unsigned(0, n, Endianness::Little), x);
+        tm.tape.read_unsigned(n, n, Endianness::Little)
+    }
+
+    fn run_unary(compiler: &TuringCompiler, n: usize, x: u64) -> u64 {
+        let tape = TuringTape::from_unsigned(x, n, Endianness::Little, 0);
+        let mut tm = TuringMachine::default().with_program(compiler.get_program()).with_tape(tape);
+        tm.run_program();
+
+        assert_eq!(tm.get_position(), 0);
+        assert!(!tm.tape.read(n));
+        tm.tape.read_unsigned(0, n, Endianness::Little)
+    }
+
+    fn test_binary(operation: Operation, expected: fn(u64, u64) -> u64) {
+        let mut rng = XorShift::new(0x5EED);
+        for n in WIDTHS {
+            let compiler = build(operation, n);
+            for _ in 0..20 {
+                let (x, y) = (rng.next_bits(n), rng.next_bits(n));
+                assert_eq!(run_binary(&compiler, n, x, y), expected(x, y) & get_mask(n), "n={} x={} y={}", n, x, y);
+            }
+            let max = get_mask(n);
+            assert_eq!(run_binary(&compiler, n, max, max), expected(max, max) & max);
+        }
+    }
+
+    fn test_unary(operation: Operation, expected: fn(u64) -> u64) {
+        let mut rng = XorShift::new(0x5EED);
+        for n in WIDTHS {
+            let compiler = build(operation, n);
+            for x in [0, 1, get_mask(n)].into_iter().chain((0..20).map(|_| rng.next_bits(n))) {
+                assert_eq!(run_unary(&compiler, n, x), expected(x) & get_mask(n), "n={} x={}", n, x);
+            }
+        }
+    }
+
+    #[test]
+    fn test_add_n() {
+        test_binary(TuringCompiler::add_n, |x, y| x.wrapping_add(y));
+    }
+
+    #[test]
+    fn test_sub_n() {
+        test_binary(TuringCompiler::sub_n, |x, y| y.wrapping_sub(x));
+    }
+
+    #[test]
+    fn test_increment_n() {
+        test_unary(TuringCompiler::increment_n, |x| x.wrapping_add(1));
+    }
+
+    #[test]
+    fn test_decrement_n() {
+        test_unary(TuringCompiler::decrement_n, |x| x.wrapping_sub(1));
+    }
+
+    #[test]
+    fn test_negate_n() {
+        test_unary(TuringCompiler::negate_n, |x| x.wrapping_neg());
+    }
+
+    /// Runs the program on the given fields laid out next to each other and returns all fields afterwards.
+    fn run_fields(compiler: &TuringCompiler, n: usize, fields: &[u64]) -> Vec<u64> {
+        let mut tape = TuringTape::default();
+        for (i, value) in fields.iter().enumerate() {
+            tape.write_unsigned(i * n, *value, n, Endianness::Little);
+        }
+        let mut tm = TuringMachine::default().with_program(compiler.get_program()).with_tape(tape);
+        tm.run_program();
+
+        assert_eq!(tm.get_position(), 0);
+        (0..fields.len())
+            .map(|i| tm.tape.read_unsigned(i * n, n, Endianness::Little))
+            .collect()
+    }
+
+    #[test]
+    fn test_multiply_n() {
+        let mut rng = XorShift::new(0x5EED);
+        for n in [1, 2, 5, 8] {
+            let compiler = build(TuringCompiler::multiply_n, n);
+            for _ in 0..10 {
+                let (x, y) = (rng.next_bits(n), rng.next_bits(n));
+                // The product region starts with garbage, it has to be cleared first
+                let fields = run_fields(&compiler, n, &[x, y, get_mask(n), get_mask(n)]);
+                let product = fields[2] | fields[3] << n;
+                assert_eq!((fields[0], fields[1], product), (x, y, x * y), "n={} x={} y={}", n, x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_divide_n() {
+        let mut rng = XorShift::new(0x5EED);
+        for n in [1, 2, 5, 8] {
+            let compiler = build(TuringCompiler::divide_n, n);
+            for _ in 0..10 {
+                let (d, a) = (rng.next_bits(n).max(1), rng.next_bits(n));
+                let fields = run_fields(&compiler, n, &[d, a, 0]);
+                assert_eq!(fields, vec![d, a % d, a / d], "n={} a={} d={}", n, a, d);
+            }
+
+            let fields = run_fields(&compiler, n, &[0, 1, 0]);
+            assert_eq!(fields, vec![0, 1, get_mask(n)]);
+        }
+    }
+
+    /// The table of the README: n, multiply states and steps, divide states and steps.
+    const COSTS: [(usize, usize, usize, usize, usize); 5] = [
+        (1, 28, 18, 37, 15),
+        (2, 148, 73, 193, 65),
+        (4, 1024, 411, 1300, 417),
+        (8, 7840, 2839, 9718, 3137),
+        (16, 61888, 21423, 75562, 24705),
+    ];
+
+    /// The states of the program, including halt, and the steps it takes on the fields until it halts.
+    fn measure(compiler: &TuringCompiler, n: usize, fields: &[u64]) -> (usize, usize) {
+        let mut tape = TuringTape::default();
+        for (i, value) in fields.iter().enumerate() {
+            tape.write_unsigned(i * n, *value, n, Endianness::Little);
+        }
+        let program = compiler.get_program();
+        let states = program.get_states().len();
+        assert_eq!(states, compiler.get_state_count() + 1);
+        let mut tm = TuringMachine::default().with_program(program).with_tape(tape);
+        let mut steps = 0;
+        while tm.program_step() {
+            steps += 1;
+        }
+        (states, steps)
+    }
+
+    #[test]
+    fn test_costs() {
+        // All bits of y set for multiplication, d at its maximum and a = 0 for division, so every step restores
+        for (n, multiply_states, multiply_steps, divide_states, divide_steps) in COSTS {
+            let multiply = build(TuringCompiler::multiply_n, n);
+            assert_eq!(measure(&multiply, n, &[0, get_mask(n), 0, 0]), (multiply_states, multiply_steps), "n={}", n);
+            let divide = build(TuringCompiler::divide_n, n);
+            assert_eq!(measure(&divide, n, &[get_mask(n), 0, 0]), (divide_states, divide_steps), "n={}", n);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use crate::compiler::layers::field::FieldLayer;
+use crate::compiler::structures::field::Field;
+use crate::compiler::structures::layout::TapeLayout;
+use crate::machine::state::State;
+
+/// Navigation between the named fields of a [`TapeLayout`].
+///
+/// The compiler keeps track of the head position relative to the start of the layout while emitting code, so it only
+/// holds for straight-line movements made through this layer. After anything that leaves the head elsewhere the
+/// position has to be set again with [`set_layout_position`](LayoutLayer::set_layout_position).
+pub trait LayoutLayer: FieldLayer {
+    fn get_layout(&self) -> &TapeLayout;
+    /// Replaces the layout, the head is assumed to be at its start.
+    fn set_layout(&mut self, layout: TapeLayout);
+    fn get_layout_position(&self) -> isize;
+    fn set_layout_position(&mut self, position: isize);
+
+    /// The current state will move the head onto the lowest bit of the named field.
+    fn goto_field(&mut self, name: &str, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let offset = self.get_layout().get_field(name).offset;
+        self.goto_offset(offset, current_state, next_state)
+    }
+
+    /// The current state will move the head to the given offset from the start of the layout.
+    fn goto_offset(&mut self, offset: isize, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let delta = offset - self.get_layout_position();
+        self.set_layout_position(offset);
+        self.move_by(delta, current_state, next_state)
+    }
+
+    /// The named field relative to the current head position, to be passed on to layers which work on fields.
+    fn get_relative_field(&self, name: &str) -> Field {
+        let field = self.get_layout().get_field(name);
+        Field::new(field.offset - self.get_layout_position(), field.width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::layers::integer::IntegerLayer;
+    use crate::compiler::layers::primitive::PrimitiveLayer;
+    use crate::compiler::layers::program_builder::ProgramBuilder;
+    use crate::compiler::turing_compiler::TuringCompiler;
+    use crate::machine::turing_machine::TuringMachine;
+    use super::*;
+
+    const ORIGIN: usize = 4;
+
+    fn run(compiler: &TuringCompiler, values: &[(&str, u64)]) -> TuringMachine {
+        let mut tm = TuringMachine::default().with_program(compiler.get_program());
+        for (name, value) in values {
+            compiler.get_layout().write_field(&mut tm.tape, ORIGIN, name, *value);
+        }
+        tm.set_head(ORIGIN);
+        tm.run_program();
+        tm
+    }
+
+    #[test]
+    fn test_goto_field() {
+        let mut compiler = TuringCompiler::default();
+        compiler.set_layout(TapeLayout::new().with_field("a", 3).with_gap("b", 2, 4).with_field("c", 2));
+        let start_state = compiler.allocate_state();
+        let done = compiler.halt(None);
+
+        let (_, mark_c) = compiler.goto_field("c", Some(start_state), None);
+        let (_, to_b) = compiler.mark(Some(mark_c), None);
+        let (_, mark_b) = compiler.goto_field("b", Some(to_b), None);
+        let (_, to_a) = compiler.mark(Some(mark_b), None);
+        compiler.goto_field("a", Some(to_a), Some(done));
+        assert_eq!(compiler.get_layout_position(), 0);
+
+        let tm = run(&compiler, &[]);
+        assert_eq!(tm.head, ORIGIN);
+        assert_eq!(compiler.get_layout().read_field(&tm.tape, ORIGIN, "b"), 1);
+        assert_eq!(compiler.get_layout().read_field(&tm.tape, ORIGIN, "c"), 1);
+    }
+
+    #[test]
+    fn test_fields_by_name() {
+        // y := x + y on fields which aren't at the head, found through the layout
+        let mut compiler = TuringCompiler::default();
+        compiler.set_layout(TapeLayout::new().with_field("flag", 1).with_field("x", 5).with_field("y", 5));
+        let start_state = compiler.allocate_state();
+        let done = compiler.halt(None);
+
+        let (_, add_state) = compiler.goto_field("x", Some(start_state), None);
+        assert_eq!(compiler.get_relative_field("y"), Field::new(5, 5));
+        let (_, back_state) = compiler.add_n(5, Some(add_state), None);
+        compiler.goto_field("flag", Some(back_state), Some(done));
+
+        let tm = run(&compiler, &[("x", 13), ("y", 9)]);
+        assert_eq!(tm.head, ORIGIN);
+        assert_eq!(compiler.get_layout().read_field(&tm.tape, ORIGIN, "x"), 13);
+        assert_eq!(compiler.get_layout().read_field(&tm.tape, ORIGIN, "y"), 22);
+    }
+}
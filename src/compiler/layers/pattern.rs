@@ -1,9 +1,12 @@
+use std::collections::HashMap;
 use crate::compiler::layers::base::BaseLayer;
 use crate::compiler::structures::pattern::Pattern;
+use crate::compiler::structures::scan_automaton::{ScanAutomaton, ScanTransition};
 use crate::enums::movement::Movement;
 use crate::machine::state::State;
 
 pub trait PatternLayer: BaseLayer {
+    /// Writes the pattern cell by cell, wildcards are skipped over without writing.
     fn write_pattern(
         &mut self,
         pattern: Pattern,
@@ -22,28 +25,21 @@ pub trait PatternLayer: BaseLayer {
             |compiler, i, iter_start, iter_end| {
                 // Invert pattern when writing it from the left
                 let current_target_bit = match write_movement {
-                    Movement::Left => pattern.get_at(pattern.len() - i - 1),
-                    _ => pattern.get_at(i)
+                    Movement::Left => pattern.get_wildcard_at(pattern.len() - i - 1),
+                    _ => pattern.get_wildcard_at(i)
                 };
-                if i < pattern.len() - 1 {
-                    compiler.write_and_move(
-                        current_target_bit,
-                        write_movement,
-                        iter_start,
-                        iter_end,
-                    )
-                } else {
-                    compiler.write_and_move(
-                        current_target_bit,
-                        final_movement,
-                        iter_start,
-                        iter_end,
-                    )
+                let movement = if i < pattern.len() - 1 { write_movement } else { final_movement };
+                match current_target_bit {
+                    Some(bit) => compiler.write_and_move(bit, movement, iter_start, iter_end),
+                    // Wildcards leave the cell as it is
+                    None => compiler.move_in_direction(movement, iter_start, iter_end),
                 }
             }
         )
     }
 
+    /// Moves in the scan direction until the whole pattern was read, then does the final movement from its last cell.
+    /// Compiled as the KMP automaton of the pattern, see [`scan_any`](PatternLayer::scan_any).
     fn scan_pattern(
         &mut self,
         pattern: Pattern,
@@ -52,53 +48,192 @@ pub trait PatternLayer: BaseLayer {
         current_state: Option<State>,
         next_state: Option<State>,
     ) -> (State, State) {
+        if pattern.is_empty() {
+            return self.idle(current_state, next_state);
+        }
+
+        let (start_state, end_states) = self.scan_any(&[pattern], scan_movement, final_movement, current_state, &[next_state]);
+        (start_state, end_states[0])
+    }
+
+    /// The automaton recognizing the patterns cell by cell in the scan direction, it runs every pattern scan.
+    fn get_scan_automaton(patterns: &[Pattern], scan_movement: Movement) -> ScanAutomaton {
+        // Scanning to the left reads the patterns back to front
+        let patterns: Vec<Pattern> = match scan_movement {
+            Movement::Left => patterns.iter().map(|pattern| pattern.reversed()).collect(),
+            _ => patterns.to_vec(),
+        };
+        ScanAutomaton::new(&patterns)
+    }
+
+    /// Moves in the scan direction until any of the patterns was read and exits through the state given for it,
+    /// after doing the final movement from its last cell. When several patterns end on the same cell the first one wins.
+    ///
+    /// All patterns are scanned for at once by their Aho–Corasick automaton, every cell is read exactly once.
+    fn scan_any(
+        &mut self,
+        patterns: &[Pattern],
+        scan_movement: Movement,
+        final_movement: Movement,
+        current_state: Option<State>,
+        next_states: &[Option<State>],
+    ) -> (State, Vec<State>) {
+        assert_eq!(patterns.len(), next_states.len(), "Expected one next state per pattern");
         let start_state = current_state.unwrap_or_else(|| self.allocate_state());
-        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+        let end_states: Vec<State> = next_states.iter()
+            .map(|state| state.unwrap_or_else(|| self.allocate_state()))
+            .collect();
 
-        self.chained_loop(
-            pattern.len(),
-            Some(start_state),
-            Some(end_state),
-            |compiler, i, iter_start, iter_end| {
-                // Invert pattern when scanning it from the left
-                let current_target_bit = match scan_movement {
-                    Movement::Left => pattern.get_at(pattern.len() - i - 1),
-                    _ => pattern.get_at(i)
-                };
-                let (branch_state, match_state, _) =
-                    if i < pattern.len() - 1 {
-                        compiler.branch_when(
-                            current_target_bit,
-                            scan_movement,
-                            scan_movement,
-                            iter_start,
-                            iter_end,
-                            Some(start_state)
-                        )
-                    } else {
-                        compiler.branch_when(
-                            current_target_bit,
-                            final_movement,
-                            scan_movement,
-                            iter_start,
-                            iter_end,
-                            Some(start_state)
-                        )
-                    };
-                (branch_state, match_state)
+        let automaton = Self::get_scan_automaton(patterns, scan_movement);
+        let mut states = vec![start_state];
+        states.extend(self.allocate_states(automaton.get_state_count() - 1));
+
+        for (i, state) in states.iter().enumerate() {
+            let [(state_unmarked, movement_unmarked), (state_marked, movement_marked)] = [false, true].map(|bit| {
+                match automaton.get_transition(i, bit) {
+                    ScanTransition::Continue(next) => (states[next], scan_movement),
+                    ScanTransition::Found(pattern) => (end_states[pattern], final_movement),
+                }
+            });
+            self.branch_move(Some(*state), Some(state_marked), Some(state_unmarked), movement_marked, movement_unmarked);
+        }
+
+        (start_state, end_states)
+    }
+
+    /// Like [`scan_pattern`](PatternLayer::scan_pattern) but reads at most `max_distance` cells, starting with the one
+    /// under the head. If the pattern doesn't end among them, the head returns to where it started and transitions to
+    /// the not found state.
+    ///
+    /// The automaton is unrolled once per cell, so this takes O(max_distance · pattern length) states.
+    #[allow(clippy::too_many_arguments)]
+    fn scan_pattern_bounded(
+        &mut self,
+        pattern: Pattern,
+        max_distance: usize,
+        scan_movement: Movement,
+        final_movement: Movement,
+        current_state: Option<State>,
+        found_state: Option<State>,
+        not_found_state: Option<State>,
+    ) -> (State, State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let found_state = found_state.unwrap_or_else(|| self.allocate_state());
+        let not_found_state = not_found_state.unwrap_or_else(|| self.allocate_state());
+
+        if max_distance == 0 || pattern.is_empty() {
+            let next_state = if pattern.is_empty() { found_state } else { not_found_state };
+            self.idle(Some(start_state), Some(next_state));
+            return (start_state, found_state, not_found_state);
+        }
+
+        let automaton = Self::get_scan_automaton(&[pattern], scan_movement);
+        let (give_up_movement, give_up_state) = self.give_up_scan(max_distance, scan_movement, not_found_state);
+
+        // Keyed by the number of cells read before and the automaton state
+        let mut states = HashMap::from([((0, 0), start_state)]);
+        let mut pending = vec![(0, 0)];
+        while let Some((distance, scan_state)) = pending.pop() {
+            let [(state_unmarked, movement_unmarked), (state_marked, movement_marked)] = [false, true].map(|bit| {
+                match automaton.get_transition(scan_state, bit) {
+                    ScanTransition::Found(_) => (found_state, final_movement),
+                    ScanTransition::Continue(_) if distance + 1 == max_distance => (give_up_state, give_up_movement),
+                    ScanTransition::Continue(next) => {
+                        let state = *states.entry((distance + 1, next)).or_insert_with(|| {
+                            pending.push((distance + 1, next));
+                            self.allocate_state()
+                        });
+                        (state, scan_movement)
+                    },
+                }
+            });
+            let state = states[&(distance, scan_state)];
+            self.branch_move(Some(state), Some(state_marked), Some(state_unmarked), movement_marked, movement_unmarked);
+        }
+
+        (start_state, found_state, not_found_state)
+    }
+
+    /// Checks whether the pattern starts under the head when reading in the given direction, without consuming it.
+    /// The head ends up where it started in both cases.
+    fn match_pattern(
+        &mut self,
+        pattern: Pattern,
+        movement: Movement,
+        current_state: Option<State>,
+        matched_state: Option<State>,
+        unmatched_state: Option<State>,
+    ) -> (State, State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let matched_state = matched_state.unwrap_or_else(|| self.allocate_state());
+        let unmatched_state = unmatched_state.unwrap_or_else(|| self.allocate_state());
+
+        if pattern.is_empty() {
+            self.idle(Some(start_state), Some(matched_state));
+            return (start_state, matched_state, unmatched_state);
+        }
+
+        // Rewind chains, the k-th state still has to move back k cells before reaching its target
+        let rewind_back = movement.get_opposite();
+        let mut rewind_matched = vec![matched_state];
+        let mut rewind_unmatched = vec![unmatched_state];
+        for k in 1..pattern.len() - 1 {
+            let (state, _) = self.move_in_direction(rewind_back, None, Some(rewind_matched[k - 1]));
+            rewind_matched.push(state);
+            let (state, _) = self.move_in_direction(rewind_back, None, Some(rewind_unmatched[k - 1]));
+            rewind_unmatched.push(state);
+        }
+
+        let mut read_state = start_state;
+        for i in 0..pattern.len() {
+            // Invert pattern when matching it from the right
+            let target_bit = match movement {
+                Movement::Left => pattern.get_wildcard_at(pattern.len() - i - 1),
+                _ => pattern.get_wildcard_at(i)
+            };
+            let (match_movement, match_state) = if i < pattern.len() - 1 {
+                (movement, self.allocate_state())
+            } else if i == 0 {
+                (Movement::Stay, matched_state)
+            } else {
+                (rewind_back, rewind_matched[i - 1])
+            };
+            let (else_movement, else_state) = if i == 0 {
+                (Movement::Stay, unmatched_state)
+            } else {
+                (rewind_back, rewind_unmatched[i - 1])
+            };
+
+            match target_bit {
+                Some(target_bit) => {
+                    self.branch_when(
+                        target_bit,
+                        match_movement,
+                        else_movement,
+                        Some(read_state),
+                        Some(match_state),
+                        Some(else_state)
+                    );
+                },
+                None => {
+                    self.move_in_direction(match_movement, Some(read_state), Some(match_state));
+                },
             }
-        );
+            read_state = match_state;
+        }
 
-        (start_state, end_state)
+        (start_state, matched_state, unmatched_state)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::compiler::layers::field::FieldLayer;
     use crate::compiler::layers::primitive::PrimitiveLayer;
     use crate::compiler::layers::program_builder::ProgramBuilder;
     use crate::compiler::turing_compiler::TuringCompiler;
     use crate::machine::turing_machine::TuringMachine;
+    use crate::machine::tape::Tape;
     use crate::machine::turing_tape::TuringTape;
     use super::*;
 
@@ -151,6 +286,25 @@ mod tests {
         assert!(tm.tape.read(8));
     }
 
+    #[test]
+    fn test_write_pattern_wildcards() {
+        let pattern: Pattern = "1?{2}0".parse().unwrap();
+        for (movement, head, expected_head) in [(Movement::Right, 0, 3), (Movement::Left, 3, 0)] {
+            let mut compiler = TuringCompiler::default();
+            let write_state = compiler.allocate_state();
+            let done = compiler.halt(None);
+            compiler.write_pattern(pattern.clone(), movement, Movement::Stay, Some(write_state), Some(done));
+
+            let mut tm = TuringMachine::default()
+                .with_program(compiler.get_program())
+                .with_tape(TuringTape::from_bit_string("0101_1"));
+            tm.set_head(head);
+            tm.run_program();
+            assert_eq!(tm.head, expected_head);
+            assert_eq!(tm.tape.read_bit_string(0, 5), "11001");
+        }
+    }
+
     #[test]
     fn test_scan_pattern() {
         let pattern = Pattern::new(vec![true, true, false, false, false, true, false, true]);
@@ -189,4 +343,200 @@ mod tests {
         tm.run_program();
         assert_eq!(tm.head, 4);
     }
+
+    fn run_scan_pattern(pattern: &Pattern, movement: Movement, tape: &str, head: usize) -> usize {
+        let mut compiler = TuringCompiler::default();
+        let scan_state = compiler.allocate_state();
+        let done = compiler.halt(None);
+        compiler.scan_pattern(pattern.clone(), movement, Movement::Stay, Some(scan_state), Some(done));
+
+        let mut tm = TuringMachine::default()
+            .with_program(compiler.get_program())
+            .with_tape(TuringTape::from_bit_string(tape));
+        tm.set_head(head);
+        tm.run_program();
+        tm.head
+    }
+
+    fn get_bit_strings(length: usize) -> impl Iterator<Item = String> {
+        (0..1usize << length).map(move |bits| (0..length).map(|i| if bits >> i & 1 == 1 { '1' } else { '0' }).collect())
+    }
+
+    #[test]
+    fn test_scan_pattern_exhaustive() {
+        // The pattern is appended (or prepended when scanning left), so there is always an occurrence
+        for pattern_length in 1..=4 {
+            for pattern_bits in get_bit_strings(pattern_length) {
+                let pattern: Pattern = pattern_bits.parse().unwrap();
+                for length in 0..=7 {
+                    for bits in get_bit_strings(length) {
+                        let tape = format!("{}{}", bits, pattern_bits);
+                        let expected = tape.find(&pattern_bits).unwrap() + pattern_length - 1;
+                        assert_eq!(run_scan_pattern(&pattern, Movement::Right, &tape, 0), expected, "{} in {}", pattern_bits, tape);
+
+                        let tape = format!("{}{}", pattern_bits, bits);
+                        let expected = tape.rfind(&pattern_bits).unwrap();
+                        assert_eq!(run_scan_pattern(&pattern, Movement::Left, &tape, tape.len() - 1), expected, "{} in {}", pattern_bits, tape);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_scan_pattern_wildcards() {
+        let matches_at = |tape: &str, pattern: &str, start: usize| {
+            tape[start..start + pattern.len()].chars().zip(pattern.chars()).all(|(bit, cell)| cell == '?' || bit == cell)
+        };
+        for pattern_length in 1..=3 {
+            for cells in 0..3usize.pow(pattern_length as u32) {
+                let pattern_string: String = (0..pattern_length).map(|i| ['0', '1', '?'][cells / 3usize.pow(i as u32) % 3]).collect();
+                let pattern: Pattern = pattern_string.parse().unwrap();
+                let occurrence = pattern_string.replace('?', "1");
+                for length in 0..=6 {
+                    for bits in get_bit_strings(length) {
+                        let tape = format!("{}{}", bits, occurrence);
+                        let expected = (0..).find(|start| matches_at(&tape, &pattern_string, *start)).unwrap() + pattern_length - 1;
+                        assert_eq!(run_scan_pattern(&pattern, Movement::Right, &tape, 0), expected, "{} in {}", pattern_string, tape);
+
+                        let tape = format!("{}{}", occurrence, bits);
+                        let expected = (0..=length).rev().find(|start| matches_at(&tape, &pattern_string, *start)).unwrap();
+                        assert_eq!(run_scan_pattern(&pattern, Movement::Left, &tape, tape.len() - 1), expected, "{} in {}", pattern_string, tape);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The cell where the first of the patterns ends and which one, the first given pattern wins ties.
+    fn find_first(tape: &str, patterns: &[String]) -> (usize, usize) {
+        (1..=tape.len())
+            .find_map(|end| patterns.iter().position(|pattern| tape[..end].ends_with(pattern.as_str())).map(|i| (end - 1, i)))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_scan_any() {
+        // Field start, record end and tape end markers, dispatched on whichever comes first
+        let patterns: Vec<String> = ["0110", "010", "111"].map(String::from).to_vec();
+        let reversed: Vec<String> = patterns.iter().map(|pattern| pattern.chars().rev().collect()).collect();
+        let bits: Vec<Pattern> = patterns.iter()
+            .map(|pattern| pattern.parse().unwrap())
+            .collect();
+
+        for movement in [Movement::Right, Movement::Left] {
+            let mut compiler = TuringCompiler::default();
+            let scan_state = compiler.allocate_state();
+            let (_, exits) = compiler.scan_any(&bits, movement, Movement::Stay, Some(scan_state), &[None, None, None]);
+            // Every exit moves further right, so the final head position tells them apart
+            for (i, exit) in exits.iter().enumerate() {
+                compiler.move_by(100 * i as isize, Some(*exit), Some(State::halt()));
+            }
+
+            for length in 0..=8 {
+                for bits in get_bit_strings(length) {
+                    // The tape end marker guarantees an occurrence
+                    let (tape, head, expected) = match movement {
+                        Movement::Left => {
+                            let tape = format!("111{}", bits);
+                            let read: String = tape.chars().rev().collect();
+                            let (end, pattern) = find_first(&read, &reversed);
+                            (tape.clone(), tape.len() - 1, (tape.len() - 1 - end, pattern))
+                        }
+                        _ => {
+                            let tape = format!("{}111", bits);
+                            (tape.clone(), 0, find_first(&tape, &patterns))
+                        }
+                    };
+
+                    let mut tm = TuringMachine::default()
+                        .with_program(compiler.get_program())
+                        .with_tape(TuringTape::from_bit_string(&tape));
+                    tm.set_head(head);
+                    tm.run_program();
+                    assert_eq!((tm.head % 100, tm.head / 100), expected, "{} {:?}", tape, movement);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_scan_pattern_bounded() {
+        let pattern: Pattern = "110".parse().unwrap();
+        let tape = "00111000011000";
+        for movement in [Movement::Right, Movement::Left] {
+            for offset in 0..tape.len() - 8 {
+                let head = if movement == Movement::Left { tape.len() - 1 - offset } else { offset };
+                for max_distance in 0..8 {
+                    let mut compiler = TuringCompiler::default();
+                    let scan_state = compiler.allocate_state();
+                    let (_, found, not_found) = compiler.scan_pattern_bounded(
+                        pattern.clone(),
+                        max_distance,
+                        movement,
+                        Movement::Stay,
+                        Some(scan_state),
+                        None,
+                        None,
+                    );
+                    // Finding the pattern moves further right, so the final head position tells the exits apart
+                    compiler.move_by(100, Some(found), Some(State::halt()));
+                    compiler.redirect_state(not_found, State::halt());
+
+                    // The cells which may be read, in the order they are read
+                    let read: Vec<usize> = match movement {
+                        Movement::Left => (head + 1 - max_distance..=head).rev().collect(),
+                        _ => (head..head + max_distance).collect(),
+                    };
+                    let read_bits: String = read.iter().map(|i| &tape[*i..*i + 1]).collect();
+                    let read_pattern = if movement == Movement::Left { "011" } else { "110" };
+                    let expected = match read_bits.find(read_pattern) {
+                        Some(start) => read[start + 2] + 100,
+                        None => head,
+                    };
+
+                    let mut tm = TuringMachine::default()
+                        .with_program(compiler.get_program())
+                        .with_tape(TuringTape::from_bit_string(tape));
+                    tm.set_head(head);
+                    tm.run_program();
+                    assert_eq!(tm.head, expected, "head={} max_distance={} {:?}", head, max_distance, movement);
+                }
+            }
+        }
+    }
+
+    fn run_match_pattern(pattern: &Pattern, movement: Movement, tape: &str, head: usize) -> (bool, usize) {
+        let mut compiler = TuringCompiler::default();
+        let match_state = compiler.allocate_state();
+        let matched = compiler.allocate_state();
+        let unmatched = compiler.allocate_state();
+        compiler.match_pattern(pattern.clone(), movement, Some(match_state), Some(matched), Some(unmatched));
+        compiler.mark(Some(matched), Some(State::halt()));
+        compiler.unmark(Some(unmatched), Some(State::halt()));
+
+        let mut tm = TuringMachine::default()
+            .with_program(compiler.get_program())
+            .with_tape(TuringTape::from_bit_string(tape));
+        tm.set_head(head);
+        tm.run_program();
+        (tm.read(), tm.head)
+    }
+
+    #[test]
+    fn test_match_pattern() {
+        let pattern = Pattern::new(vec![true, false, true]);
+
+        assert_eq!(run_match_pattern(&pattern, Movement::Right, "0101_1000", 1), (true, 1));
+        assert_eq!(run_match_pattern(&pattern, Movement::Right, "0101_1000", 2), (false, 2));
+        let wildcard_pattern: Pattern = "1?1".parse().unwrap();
+        assert_eq!(run_match_pattern(&wildcard_pattern, Movement::Right, "0111_1000", 1), (true, 1));
+        assert_eq!(run_match_pattern(&wildcard_pattern, Movement::Left, "0111_1000", 3), (true, 3));
+        assert_eq!(run_match_pattern(&wildcard_pattern, Movement::Right, "0111_1000", 3), (false, 3));
+        assert_eq!(run_match_pattern(&pattern, Movement::Right, "0100_1000", 1), (false, 1));
+        assert_eq!(run_match_pattern(&pattern, Movement::Left, "0101_1000", 3), (true, 3));
+        assert_eq!(run_match_pattern(&pattern, Movement::Left, "0111_1000", 3), (false, 3));
+        assert_eq!(run_match_pattern(&Pattern::new(vec![true]), Movement::Right, "0100", 1), (true, 1));
+        assert_eq!(run_match_pattern(&Pattern::new(vec![true]), Movement::Right, "0100", 2), (false, 2));
+    }
 }
\ No newline at end of file
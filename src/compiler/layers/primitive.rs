@@ -1,4 +1,5 @@
 use crate::compiler::layers::program_builder::ProgramBuilder;
+use crate::enums::halt_code::HaltCode;
 use crate::enums::movement::Movement;
 use crate::machine::instruction::Instruction;
 use crate::machine::state::State;
@@ -181,6 +182,30 @@ pub trait PrimitiveLayer: ProgramBuilder {
         new_state
     }
 
+    /// The given state will transition to the halt state of the code, whatever the bit under the head.
+    fn halt_with(&mut self, code: HaltCode, current_state: Option<State>) -> State {
+        let new_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let halt_state = State::halt_with(code);
+
+        let instruction_0 = Instruction::new(new_state, false, false)
+            .with_next_state(halt_state);
+        let instruction_1 = Instruction::new(new_state, true, true)
+            .with_next_state(halt_state);
+        self.add_instructions(&[instruction_0, instruction_1]);
+
+        new_state
+    }
+
+    /// The given state will halt and accept the input.
+    fn accept(&mut self, current_state: Option<State>) -> State {
+        self.halt_with(HaltCode::Accept, current_state)
+    }
+
+    /// The given state will halt and reject the input.
+    fn reject(&mut self, current_state: Option<State>) -> State {
+        self.halt_with(HaltCode::Reject, current_state)
+    }
+
     fn mark_and_move_right(&mut self, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
         let current_state = current_state.unwrap_or_else(|| self.allocate_state());
         let next_state = next_state.unwrap_or_else(|| self.allocate_state());
@@ -246,6 +271,7 @@ pub trait PrimitiveLayer: ProgramBuilder {
 mod tests {
     use crate::compiler::turing_compiler::TuringCompiler;
     use crate::machine::turing_machine::TuringMachine;
+    use crate::machine::tape::Tape;
     use crate::machine::turing_tape::TuringTape;
     use super::*;
 
@@ -417,4 +443,18 @@ mod tests {
         assert!(!tm.tape.read(8));
         assert_eq!(tm.head, 7);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_accept_and_reject() {
+        let mut compiler = TuringCompiler::default();
+
+        let start_state = compiler.allocate_state();
+        let accept_state = compiler.accept(None);
+        let reject_state = compiler.reject(None);
+        compiler.branch_move(Some(start_state), Some(accept_state), Some(reject_state), Movement::Stay, Movement::Stay);
+
+        let mut tm = TuringMachine::default().with_program(compiler.get_program());
+        assert!(tm.decides("1"));
+        assert!(!tm.decides("01"));
+    }
+}
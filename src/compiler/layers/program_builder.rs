@@ -1,11 +1,19 @@
+use crate::enums::halt_code::HaltCode;
 use crate::machine::instruction::Instruction;
 use crate::machine::state::State;
+use crate::machine::turing_program::TuringProgram;
 
 pub trait ProgramBuilder {
     fn allocate_state(&mut self) -> State;
+    /// The number of states allocated so far.
+    fn get_state_count(&self) -> usize;
     fn get_halt_state(&self) -> State;
     fn get_instruction(&self, state: State, read_bit: bool) -> Option<&Instruction>;
     fn add_instruction(&mut self, instruction: Instruction);
+    /// Merges `from` into `to`, every transition into `from` will lead to `to` instead.
+    fn redirect_state(&mut self, from: State, to: State);
+    /// Exchanges the numbers of two states.
+    fn swap_states(&mut self, a: State, b: State);
 
     fn allocate_states(&mut self, count: usize) -> Vec<State> {
         (0..count).map(|_| self.allocate_state()).collect()
@@ -14,4 +22,26 @@ pub trait ProgramBuilder {
     fn add_instructions(&mut self, instructions: &[Instruction]) {
         instructions.iter().for_each(|i| self.add_instruction(*i));
     }
+
+    /// Embeds a prebuilt program, its `State(0)` becomes the current state and halting continues with the next state.
+    /// All other states of the program are relocated to freshly allocated states, halting with any other
+    /// [`HaltCode`] still stops the machine.
+    fn import_program(&mut self, program: &TuringProgram, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        let mut states = vec![start_state];
+        states.extend(self.allocate_states(program.get_state_span() - 1));
+        let relocated = program.map_states(|state| match state.get_halt_code() {
+            Some(HaltCode::Halt) => end_state,
+            Some(_) => state,
+            None => states[state.get()],
+        });
+
+        relocated.get_sorted_instructions()
+            .into_iter()
+            .for_each(|instruction| self.add_instruction(*instruction));
+
+        (start_state, end_state)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use crate::compiler::layers::pattern::PatternLayer;
+use crate::compiler::structures::pattern::Pattern;
+use crate::compiler::structures::regex::Regex;
+use crate::enums::movement::Movement;
+use crate::machine::state::State;
+
+/// Regular expressions over the cells of the tape, compiled into DFAs with one state per DFA state.
+/// When reading to the left the regex still describes the cells from left to right.
+pub trait RegexLayer: PatternLayer {
+    /// Moves in the scan direction until a word of the regex was read, then does the final movement from its last cell.
+    /// The word can start anywhere from the cell under the head, the first one to end is found.
+    fn scan_regex(
+        &mut self,
+        regex: &Regex,
+        scan_movement: Movement,
+        final_movement: Movement,
+        current_state: Option<State>,
+        next_state: Option<State>,
+    ) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        let dfa = match scan_movement {
+            Movement::Left => regex.reversed().to_search_dfa(),
+            _ => regex.to_search_dfa(),
+        };
+        assert!(!dfa.is_accepting(0), "The regex matches the empty word, so there is nothing to scan for");
+
+        // Accepting DFA states are never entered, the scan is over by then
+        let mut states = HashMap::from([(0, start_state)]);
+        let mut pending = vec![0];
+        while let Some(dfa_state) = pending.pop() {
+            let [(state_unmarked, movement_unmarked), (state_marked, movement_marked)] = [false, true].map(|bit| {
+                let next = dfa.get_transition(dfa_state, bit);
+                if dfa.is_accepting(next) {
+                    return (end_state, final_movement);
+                }
+                let state = *states.entry(next).or_insert_with(|| {
+                    pending.push(next);
+                    self.allocate_state()
+                });
+                (state, scan_movement)
+            });
+            self.branch_move(Some(states[&dfa_state]), Some(state_marked), Some(state_unmarked), movement_marked, movement_unmarked);
+        }
+
+        (start_state, end_state)
+    }
+
+    /// Reads the input starting under the head in the given direction up to the first occurrence of the terminator,
+    /// then transitions to the accept state if the input is a word of the regex and to the reject state otherwise.
+    /// The head ends on the last cell of the terminator read.
+    ///
+    /// The input is decided with a delay of the terminator length, since the last bits read might turn out to be the
+    /// terminator. Those are carried in the state, so this takes O(DFA states · 2^terminator length) states.
+    fn decide_regex(
+        &mut self,
+        regex: &Regex,
+        terminator: Pattern,
+        movement: Movement,
+        current_state: Option<State>,
+        accept_state: Option<State>,
+        reject_state: Option<State>,
+    ) -> (State, State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let accept_state = accept_state.unwrap_or_else(|| self.allocate_state());
+        let reject_state = reject_state.unwrap_or_else(|| self.allocate_state());
+        assert!(!terminator.is_empty(), "The terminator can't be empty");
+
+        let (dfa, terminator) = match movement {
+            Movement::Left => (regex.reversed().to_dfa(), terminator.reversed()),
+            _ => (regex.to_dfa(), terminator),
+        };
+
+        // Keyed by the DFA state and the bits read after the input it has seen
+        let mut states: HashMap<(usize, Vec<bool>), State> = HashMap::from([((0, Vec::new()), start_state)]);
+        let mut pending = vec![(0, Vec::new())];
+        while let Some((dfa_state, window)) = pending.pop() {
+            let [(state_unmarked, movement_unmarked), (state_marked, movement_marked)] = [false, true].map(|bit| {
+                let mut next_window = window.clone();
+                next_window.push(bit);
+                if terminator.matches(&next_window) {
+                    let decision = if dfa.is_accepting(dfa_state) { accept_state } else { reject_state };
+                    return (decision, Movement::Stay);
+                }
+
+                let mut next_dfa_state = dfa_state;
+                if next_window.len() == terminator.len() {
+                    next_dfa_state = dfa.get_transition(dfa_state, next_window.remove(0));
+                }
+                let key = (next_dfa_state, next_window);
+                let state = match states.get(&key) {
+                    Some(state) => *state,
+                    None => {
+                        let state = self.allocate_state();
+                        states.insert(key.clone(), state);
+                        pending.push(key);
+                        state
+                    }
+                };
+                (state, movement)
+            });
+            let current = states[&(dfa_state, window)];
+            self.branch_move(Some(current), Some(state_marked), Some(state_unmarked), movement_marked, movement_unmarked);
+        }
+
+        (start_state, accept_state, reject_state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::layers::field::FieldLayer;
+    use crate::compiler::layers::program_builder::ProgramBuilder;
+    use crate::compiler::turing_compiler::TuringCompiler;
+    use crate::machine::tape::Tape;
+    use crate::machine::turing_machine::TuringMachine;
+    use crate::machine::turing_tape::TuringTape;
+    use crate::test_utils::XorShift;
+    use super::*;
+
+    /// Regexes and a word of each, which is placed on the tape to guarantee a match.
+    const REGEXES: [(&str, &str); 7] = [
+        ("11", "11"),
+        ("10*1", "1001"),
+        ("(01)+0", "01010"),
+        ("1{3}|00", "00"),
+        ("0(1|00)1", "0001"),
+        (".1.", "010"),
+        ("(0|11)*1", "1"),
+    ];
+
+    fn get_random_bits(rng: &mut XorShift, length: usize) -> String {
+        (0..length).map(|_| if rng.next_u64() & 1 == 1 { '1' } else { '0' }).collect()
+    }
+
+    fn run(compiler: &TuringCompiler, tape: &str, head: usize) -> usize {
+        let mut tm = TuringMachine::default()
+            .with_program(compiler.get_program())
+            .with_tape(TuringTape::from_bit_string(tape));
+        tm.set_head(head);
+        tm.run_program();
+        tm.head
+    }
+
+    #[test]
+    fn test_scan_regex() {
+        let mut rng = XorShift::new(45);
+        for (regex, word) in REGEXES {
+            let reference = ::regex::Regex::new(&format!("^(?:{})$", regex)).unwrap();
+            let matches = |tape: &str, start: usize, end: usize| reference.is_match(&tape[start..=end]);
+
+            for movement in [Movement::Right, Movement::Left] {
+                let mut compiler = TuringCompiler::default();
+                let scan_state = compiler.allocate_state();
+                compiler.scan_regex(&regex.parse().unwrap(), movement, Movement::Stay, Some(scan_state), Some(State::halt()));
+
+                for _ in 0..50 {
+                    let bits = get_random_bits(&mut rng, 12);
+                    let (tape, head, expected) = match movement {
+                        Movement::Left => {
+                            let tape = format!("{}{}", word, bits);
+                            let head = tape.len() - 1;
+                            // The first cell read to the left which starts a word ending before the head
+                            let expected = (0..=head).rev().find(|start| (*start..=head).any(|end| matches(&tape, *start, end)));
+                            (tape, head, expected)
+                        }
+                        _ => {
+                            let tape = format!("{}{}", bits, word);
+                            let expected = (0..tape.len()).find(|end| (0..=*end).any(|start| matches(&tape, start, *end)));
+                            (tape, 0, expected)
+                        }
+                    };
+                    assert_eq!(run(&compiler, &tape, head), expected.unwrap(), "{} in {} {:?}", regex, tape, movement);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_decide_regex() {
+        let mut rng = XorShift::new(46);
+        let terminator = "0110";
+        for (regex, _) in REGEXES.iter().chain(&[("", ""), ("(0|1)*", ""), ("1*0?", "")]) {
+            let reference = ::regex::Regex::new(&format!("^(?:{})$", regex)).unwrap();
+
+            for movement in [Movement::Right, Movement::Left] {
+                let mut compiler = TuringCompiler::default();
+                let decide_state = compiler.allocate_state();
+                let (_, accept_state, reject_state) = compiler.decide_regex(
+                    &regex.parse().unwrap(),
+                    terminator.parse().unwrap(),
+                    movement,
+                    Some(decide_state),
+                    None,
+                    None,
+                );
+                // Accepting moves further right, so the final head position tells the exits apart
+                compiler.move_by(100, Some(accept_state), Some(State::halt()));
+                compiler.redirect_state(reject_state, State::halt());
+
+                for _ in 0..100 {
+                    let length = (rng.next_u64() % 10) as usize;
+                    let bits = get_random_bits(&mut rng, length);
+                    let (tape, input, head, end) = match movement {
+                        Movement::Left => {
+                            let tape = format!("{}{}", terminator, bits);
+                            let start = tape.rfind(terminator).unwrap();
+                            (tape.clone(), tape[start + terminator.len()..].to_string(), tape.len() - 1, start)
+                        }
+                        _ => {
+                            let tape = format!("{}{}", bits, terminator);
+                            let start = tape.find(terminator).unwrap();
+                            (tape.clone(), tape[..start].to_string(), 0, start + terminator.len() - 1)
+                        }
+                    };
+                    let expected = end + if reference.is_match(&input) { 100 } else { 0 };
+                    assert_eq!(run(&compiler, &tape, head), expected, "{} on {} {:?}", regex, input, movement);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use crate::compiler::layers::field::FieldLayer;
+use crate::compiler::structures::field::Field;
+use crate::machine::state::State;
+
+/// Block operations on regions of the tape, the offsets are relative to the head which returns to where it started.
+/// The head shuttles between source and destination for every cell and carries the bit in its state.
+///
+/// The offsets are known when compiling, so every trip is spelled out in states and a copy takes about
+/// 3 · len · distance states. A shuttle which finds its way back by marker cells would need O(len) states, but the tape
+/// only holds 0 and 1, so a marker can't be told apart from the data without a free cell next to every bit.
+pub trait RegionLayer: FieldLayer {
+    /// Copies len cells from the source to the destination offset.
+    /// Overlapping regions are copied as if the source was read completely first.
+    /// Takes 3 · len states more for every cell of distance between source and destination, see the trait.
+    fn copy_region(
+        &mut self,
+        source_offset: isize,
+        len: usize,
+        destination_offset: isize,
+        current_state: Option<State>,
+        next_state: Option<State>,
+    ) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        let source = Field::new(source_offset, len);
+        let destination = Field::new(destination_offset, len);
+        let copy = |_, _, bits: &[bool]| ((), bits[0]);
+        // Start at the end which is overwritten first, so no cell is overwritten before it was read
+        if destination_offset > source_offset {
+            self.walk_fields_downwards(&[source], Some(destination), (), copy, Some(start_state), |_| end_state);
+        } else {
+            self.walk_fields(&[source], Some(destination), (), copy, Some(start_state), |_| end_state);
+        }
+
+        (start_state, end_state)
+    }
+
+    /// Copies len cells from the source to the destination offset and clears the source cells which weren't overwritten.
+    fn move_region(
+        &mut self,
+        source_offset: isize,
+        len: usize,
+        destination_offset: isize,
+        current_state: Option<State>,
+        next_state: Option<State>,
+    ) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        let source = Field::new(source_offset, len);
+        let destination = Field::new(destination_offset, len);
+        let remainder = if !source.overlaps(&destination) {
+            source
+        } else if destination_offset > source_offset {
+            Field::new(source_offset, (destination_offset - source_offset) as usize)
+        } else {
+            Field::new(destination.get_end(), (source_offset - destination_offset) as usize)
+        };
+
+        let (_, clear_state) = self.copy_region(source_offset, len, destination_offset, Some(start_state), None);
+        self.clear_field(remainder, Some(clear_state), Some(end_state));
+
+        (start_state, end_state)
+    }
+
+    /// Clears len cells starting at the head.
+    fn clear_region(&mut self, len: usize, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        self.clear_field(Field::new(0, len), current_state, next_state)
+    }
+
+    fn clear_field(&mut self, field: Field, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+
+        self.walk_fields(&[], Some(field), (), |_, _, _| ((), false), Some(start_state), |_| end_state);
+
+        (start_state, end_state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::layers::primitive::PrimitiveLayer;
+    use crate::compiler::layers::program_builder::ProgramBuilder;
+    use crate::compiler::turing_compiler::TuringCompiler;
+    use crate::machine::turing_machine::TuringMachine;
+    use crate::machine::tape::Tape;
+    use crate::machine::turing_tape::TuringTape;
+    use crate::test_utils::XorShift;
+    use super::*;
+
+    const BASE: usize = 16;
+    const TAPE_LENGTH: usize = 40;
+
+    type RegionOperation = fn(&mut TuringCompiler, isize, usize, isize, Option<State>, Option<State>) -> (State, State);
+
+    fn run(operation: RegionOperation, source: isize, len: usize, destination: isize, cells: &[bool]) -> Vec<bool> {
+        let mut compiler = TuringCompiler::default();
+        let start_state = compiler.allocate_state();
+        let done = compiler.halt(None);
+        operation(&mut compiler, source, len, destination, Some(start_state), Some(done));
+
+        let bits: String = cells.iter().map(|bit| if *bit { '1' } else { '0' }).collect();
+        let mut tm = TuringMachine::default()
+            .with_program(compiler.get_program())
+            .with_tape(TuringTape::from_bit_string(&bits));
+        tm.set_head(BASE);
+        tm.run_program();
+
+        assert_eq!(tm.head, BASE);
+        (0..TAPE_LENGTH).map(|i| tm.tape.read(i)).collect()
+    }
+
+    fn test_region(operation: RegionOperation, expected: fn(&mut Vec<bool>, usize, usize, usize)) {
+        let mut rng = XorShift::new(0xB10C);
+        for len in 0..=6 {
+            for source in [-3, 0, 4] {
+                for destination in -9..=9 {
+                    let cells: Vec<bool> = (0..TAPE_LENGTH).map(|_| rng.next_u64() & 1 == 1).collect();
+                    let mut expected_cells = cells.clone();
+                    let (from, to) = ((BASE as isize + source) as usize, (BASE as isize + destination) as usize);
+                    expected(&mut expected_cells, from, len, to);
+
+                    let result = run(operation, source, len, destination, &cells);
+                    assert_eq!(result, expected_cells, "len={} source={} destination={}", len, source, destination);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_copy_region() {
+        test_region(TuringCompiler::copy_region, |cells, from, len, to| {
+            cells.copy_within(from..from + len, to);
+        });
+    }
+
+    #[test]
+    fn test_copy_region_states() {
+        let get_states = |source_offset, len, distance| {
+            let mut compiler = TuringCompiler::default();
+            compiler.copy_region(source_offset, len, source_offset + distance, None, None);
+            compiler.get_state_count()
+        };
+
+        // Once the regions are apart and away from the head, every further cell of distance costs 3 states for every cell
+        // of the region
+        for len in 1..=8 {
+            for source_offset in [-5, 0, 3] {
+                for distance in len as isize + 8..=24 {
+                    let step = get_states(source_offset, len, distance + 1) - get_states(source_offset, len, distance);
+                    assert_eq!(step, 3 * len, "len {}, distance {}, source {}", len, distance, source_offset);
+                    let step = get_states(source_offset, len, -distance - 1) - get_states(source_offset, len, -distance);
+                    assert_eq!(step, 3 * len, "len {}, distance {}, source {}", len, -distance, source_offset);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_move_region() {
+        test_region(TuringCompiler::move_region, |cells, from, len, to| {
+            let moved = cells[from..from + len].to_vec();
+            cells[from..from + len].fill(false);
+            cells[to..to + len].copy_from_slice(&moved);
+        });
+    }
+
+    #[test]
+    fn test_clear_region() {
+        let mut compiler = TuringCompiler::default();
+        let start_state = compiler.allocate_state();
+        let done = compiler.halt(None);
+        compiler.clear_region(3, Some(start_state), Some(done));
+
+        let mut tm = TuringMachine::default()
+            .with_program(compiler.get_program())
+            .with_tape(TuringTape::from_bit_string("11111"));
+        tm.set_head(1);
+        tm.run_program();
+        assert_eq!(tm.head, 1);
+        assert_eq!(tm.tape.read_bit_string(0, 5), "10001");
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+use crate::compiler::layers::field::FieldLayer;
+use crate::compiler::layers::pattern::PatternLayer;
+use crate::compiler::structures::pattern::Pattern;
+use crate::compiler::structures::scan_automaton::ScanTransition;
+use crate::enums::movement::Movement;
+use crate::machine::instruction::Instruction;
+use crate::machine::state::State;
+
+/// Inserts or deletes cells at the head by shifting everything up to a terminator to the right or left.
+///
+/// The terminator is a marker pattern or a run of blank cells (a pattern of zeros) ending the shifted contents.
+/// Its first occurrence is located by the automaton of [`scan_pattern`](PatternLayer::scan_pattern), which runs while
+/// streaming over the contents, and is shifted along. A separate scan first would lose the start, since nothing marks
+/// it on the tape. The head ends on the cell right after the shifted terminator.
+pub trait TapeShiftLayer: PatternLayer + FieldLayer {
+    /// Shifts the contents starting at the head right by k cells and leaves k blank cells at the head.
+    /// The k cells after the terminator are overwritten.
+    ///
+    /// The last k bits read are carried in the state, so this takes O(2^k · terminator length) states.
+    fn insert_gap(&mut self, k: usize, terminator: Pattern, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+        assert!(!terminator.is_empty(), "The terminator can't be empty");
+
+        let automaton = Self::get_scan_automaton(&[terminator], Movement::Right);
+        let mut streams: HashMap<(Vec<bool>, usize), State> = HashMap::new();
+        let mut flushes: HashMap<Vec<bool>, State> = HashMap::new();
+        let mut pending = vec![(vec![false; k], 0)];
+        streams.insert((vec![false; k], 0), start_state);
+
+        // Every cell is replaced by the oldest buffered bit while the bit read is buffered
+        while let Some((buffer, scan_state)) = pending.pop() {
+            let state = streams[&(buffer.clone(), scan_state)];
+            for read_bit in [false, true] {
+                let mut next_buffer = buffer.clone();
+                next_buffer.push(read_bit);
+                let write_bit = next_buffer.remove(0);
+
+                let next_state = match automaton.get_transition(scan_state, read_bit) {
+                    ScanTransition::Found(_) => self.flush_buffer(&next_buffer, end_state, &mut flushes),
+                    ScanTransition::Continue(next_scan_state) => match streams.get(&(next_buffer.clone(), next_scan_state)) {
+                        Some(next_state) => *next_state,
+                        None => {
+                            let next_state = self.allocate_state();
+                            streams.insert((next_buffer.clone(), next_scan_state), next_state);
+                            pending.push((next_buffer, next_scan_state));
+                            next_state
+                        }
+                    },
+                };
+
+                self.add_instruction(
+                    Instruction::new(state, read_bit, write_bit)
+                        .with_movement(Movement::Right)
+                        .with_next_state(next_state)
+                );
+            }
+        }
+
+        (start_state, end_state)
+    }
+
+    /// Writes the buffered bits moving right, shared between all buffers with the same contents.
+    fn flush_buffer(&mut self, buffer: &[bool], end_state: State, flushes: &mut HashMap<Vec<bool>, State>) -> State {
+        let Some((first, rest)) = buffer.split_first() else {
+            return end_state;
+        };
+        if let Some(state) = flushes.get(buffer) {
+            return *state;
+        }
+
+        let next_state = self.flush_buffer(rest, end_state, flushes);
+        let (state, _) = self.write_and_move(*first, Movement::Right, None, Some(next_state));
+        flushes.insert(buffer.to_vec(), state);
+        state
+    }
+
+    /// Deletes k cells at the head by shifting the contents after them left by k cells.
+    /// The terminator is searched from the first cell after the deleted ones and the k cells it leaves behind are cleared.
+    ///
+    /// Every cell is carried left on its own, so this takes O(k · terminator length) states and O(k) steps per cell.
+    fn delete_cells(&mut self, k: usize, terminator: Pattern, current_state: Option<State>, next_state: Option<State>) -> (State, State) {
+        let start_state = current_state.unwrap_or_else(|| self.allocate_state());
+        let end_state = next_state.unwrap_or_else(|| self.allocate_state());
+        assert!(!terminator.is_empty(), "The terminator can't be empty");
+
+        let automaton = Self::get_scan_automaton(&[terminator], Movement::Right);
+        let read_states = self.allocate_states(automaton.get_state_count());
+        self.move_by(k as isize, Some(start_state), Some(read_states[0]));
+
+        // Keyed by the bit and the scan state after it, none once the terminator is complete
+        let mut carries: HashMap<(bool, Option<usize>), State> = HashMap::new();
+        for (scan_state, read_state) in read_states.iter().enumerate() {
+            for read_bit in [false, true] {
+                let next_scan_state = match automaton.get_transition(scan_state, read_bit) {
+                    ScanTransition::Continue(next_scan_state) => Some(next_scan_state),
+                    ScanTransition::Found(_) => None,
+                };
+                let next_state = match carries.get(&(read_bit, next_scan_state)) {
+                    Some(carry_state) => *carry_state,
+                    None => {
+                        let after_state = next_scan_state.map(|next_scan_state| read_states[next_scan_state]);
+                        let carry_state = self.carry_cell(k, read_bit, after_state, end_state);
+                        carries.insert((read_bit, next_scan_state), carry_state);
+                        carry_state
+                    }
+                };
+
+                let movement = if k == 0 { Movement::Right } else { Movement::Left };
+                self.add_instruction(
+                    Instruction::new(*read_state, read_bit, read_bit)
+                        .with_movement(movement)
+                        .with_next_state(next_state)
+                );
+            }
+        }
+
+        (start_state, end_state)
+    }
+
+    /// Entered one cell left of the cell the bit was read from, writes the bit k cells left of it and continues one
+    /// cell right of it in the next state. Without a next state the terminator was complete and the tail gets cleared.
+    fn carry_cell(&mut self, k: usize, bit: bool, next_state: Option<State>, end_state: State) -> State {
+        if k == 0 {
+            return next_state.unwrap_or(end_state);
+        }
+
+        let (carry_state, write_state) = self.move_by(1 - k as isize, None, None);
+        let (_, after_write_state) = self.write_and_move(bit, Movement::Right, Some(write_state), None);
+        match next_state {
+            Some(next_state) => {
+                self.move_by(k as isize, Some(after_write_state), Some(next_state));
+            },
+            None => {
+                let (_, return_state) = self.clear_cells_right(k, after_write_state);
+                self.move_by(-(k as isize), Some(return_state), Some(end_state));
+            },
+        }
+        carry_state
+    }
+
+    /// Clears k cells moving right, returns the first state and the state after the last cell.
+    fn clear_cells_right(&mut self, k: usize, current_state: State) -> (State, State) {
+        let mut state = current_state;
+        for _ in 0..k {
+            let (_, next_state) = self.write_and_move(false, Movement::Right, Some(state), None);
+            state = next_state;
+        }
+        (current_state, state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::layers::base::BaseLayer;
+    use crate::compiler::layers::primitive::PrimitiveLayer;
+    use crate::compiler::layers::program_builder::ProgramBuilder;
+    use crate::compiler::turing_compiler::TuringCompiler;
+    use crate::machine::turing_machine::TuringMachine;
+    use crate::machine::tape::Tape;
+    use crate::machine::turing_tape::TuringTape;
+    use crate::test_utils::XorShift;
+    use super::*;
+
+    const TAPE_LENGTH: usize = 48;
+
+    fn find(cells: &[bool], pattern: &Pattern, from: usize) -> usize {
+        (from..).find(|i| pattern.matches(&cells[*i..])).unwrap()
+    }
+
+    type ShiftOperation = fn(&mut TuringCompiler, usize, Pattern, Option<State>, Option<State>) -> (State, State);
+
+    fn run(operation: ShiftOperation, k: usize, terminator: &Pattern, cells: &[bool], head: usize) -> (Vec<bool>, usize) {
+        let mut compiler = TuringCompiler::default();
+        let start_state = compiler.allocate_state();
+        let done = compiler.halt(None);
+        operation(&mut compiler, k, terminator.clone(), Some(start_state), Some(done));
+
+        let mut tape = TuringTape::default();
+        cells.iter().enumerate().filter(|(_, bit)| **bit).for_each(|(i, _)| tape.set(i));
+        let mut tm = TuringMachine::default().with_program(compiler.get_program()).with_tape(tape);
+        tm.set_head(head);
+        tm.run_program();
+
+        ((0..TAPE_LENGTH).map(|i| tm.tape.read(i)).collect(), tm.head)
+    }
+
+    /// Random contents which contain the terminator at a random position.
+    fn get_cells(rng: &mut XorShift, terminator: &Pattern) -> Vec<bool> {
+        let mut cells: Vec<bool> = (0..TAPE_LENGTH).map(|_| rng.next_u64() & 1 == 1).collect();
+        let end = 12 + (rng.next_u64() % 12) as usize;
+        for (i, cell) in terminator.get().iter().enumerate() {
+            cells[end + i] = cell.unwrap_or(cells[end + i]);
+        }
+        cells
+    }
+
+    #[test]
+    fn test_insert_gap() {
+        let mut rng = XorShift::new(0x6A9);
+        for terminator in ["0110", "000", "1", "01?1"].map(|terminator| terminator.parse::<Pattern>().unwrap()) {
+            for k in 0..=3 {
+                for _ in 0..20 {
+                    let cells = get_cells(&mut rng, &terminator);
+                    let head = 2;
+                    let end = find(&cells, &terminator, head) + terminator.len();
+
+                    let mut expected = cells.clone();
+                    expected.copy_within(head..end, head + k);
+                    expected[head..head + k].fill(false);
+
+                    let (result, result_head) = run(TuringCompiler::insert_gap, k, &terminator, &cells, head);
+                    assert_eq!(result, expected, "k={} terminator={:?}", k, terminator);
+                    assert_eq!(result_head, end + k);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_delete_cells() {
+        let mut rng = XorShift::new(0xDE1);
+        for terminator in ["0110", "000", "1", "01?1"].map(|terminator| terminator.parse::<Pattern>().unwrap()) {
+            for k in 0..=3 {
+                for _ in 0..20 {
+                    let cells = get_cells(&mut rng, &terminator);
+                    let head = 2;
+                    let end = find(&cells, &terminator, head + k) + terminator.len();
+
+                    let mut expected = cells.clone();
+                    expected.copy_within(head + k..end, head);
+                    expected[end - k..end].fill(false);
+
+                    let (result, result_head) = run(TuringCompiler::delete_cells, k, &terminator, &cells, head);
+                    assert_eq!(result, expected, "k={} terminator={:?}", k, terminator);
+                    assert_eq!(result_head, end - k);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_insert_then_delete() {
+        let terminator: Pattern = "000".parse().unwrap();
+        let mut compiler = TuringCompiler::default();
+        let (_, find_start) = compiler.insert_gap(3, terminator.clone(), None, None);
+        let (_, skip_marker) = compiler.scan_pattern("110".parse().unwrap(), Movement::Left, Movement::Stay, Some(find_start), None);
+        let (_, delete_state) = compiler.move_right_x(2, Some(skip_marker), None);
+        let (_, done) = compiler.delete_cells(3, terminator, Some(delete_state), None);
+        compiler.halt(Some(done));
+
+        let mut tm = TuringMachine::default()
+            .with_program(compiler.get_program())
+            .with_tape(TuringTape::from_bit_string("11_1010_1000"));
+        tm.set_head(2);
+        tm.run_program();
+        assert_eq!(tm.tape.read_bit_string(0, 16), "1110101000000000");
+        assert_eq!(tm.head, 10);
+    }
+}
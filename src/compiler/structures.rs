@@ -1 +1,7 @@
-pub mod pattern;
\ No newline at end of file
+pub mod pattern;
+pub mod fragment;
+pub mod field;
+pub mod layout;
+pub mod scan_automaton;
+pub mod dfa;
+pub mod regex;
\ No newline at end of file
@@ -0,0 +1,31 @@
+/// A deterministic finite automaton over single bits, state 0 is the start.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dfa {
+    transitions: Vec<[usize; 2]>,
+    accepting: Vec<bool>,
+}
+
+impl Dfa {
+    pub fn new(transitions: Vec<[usize; 2]>, accepting: Vec<bool>) -> Self {
+        assert_eq!(transitions.len(), accepting.len(), "Expected one accepting flag per state");
+        Self { transitions, accepting }
+    }
+
+    pub fn get_state_count(&self) -> usize {
+        self.transitions.len()
+    }
+
+    pub fn get_transition(&self, state: usize, bit: bool) -> usize {
+        self.transitions[state][bit as usize]
+    }
+
+    pub fn is_accepting(&self, state: usize) -> bool {
+        self.accepting[state]
+    }
+
+    /// Whether the automaton accepts after reading all bits.
+    pub fn accepts(&self, bits: impl IntoIterator<Item = bool>) -> bool {
+        let state = bits.into_iter().fold(0, |state, bit| self.get_transition(state, bit));
+        self.is_accepting(state)
+    }
+}
@@ -0,0 +1,27 @@
+/// A run of cells on the tape holding one value, least significant bit first.
+/// The offset is relative to the head position at which the layer using the field is entered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field {
+    pub offset: isize,
+    pub width: usize,
+}
+
+impl Field {
+    pub fn new(offset: isize, width: usize) -> Self {
+        Self { offset, width }
+    }
+
+    /// The offset of the i-th bit.
+    pub fn get_cell(&self, i: usize) -> isize {
+        self.offset + i as isize
+    }
+
+    /// The offset of the cell right after the field.
+    pub fn get_end(&self) -> isize {
+        self.get_cell(self.width)
+    }
+
+    pub fn overlaps(&self, other: &Field) -> bool {
+        self.offset < other.get_end() && other.offset < self.get_end()
+    }
+}
@@ -0,0 +1,20 @@
+use crate::machine::state::State;
+
+/// A piece of a program with a single entry and a single exit state, see [`FragmentLayer`](crate::compiler::layers::fragment::FragmentLayer).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fragment {
+    pub entry: State,
+    pub exit: State,
+}
+
+impl Fragment {
+    pub fn new(entry: State, exit: State) -> Self {
+        Self { entry, exit }
+    }
+}
+
+impl From<(State, State)> for Fragment {
+    fn from((entry, exit): (State, State)) -> Self {
+        Self::new(entry, exit)
+    }
+}
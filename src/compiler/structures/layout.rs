@@ -0,0 +1,91 @@
+use crate::compiler::structures::field::Field;
+use crate::enums::endianness::Endianness;
+use crate::machine::tape::Tape;
+
+/// Named fields placed one after another on the tape, starting at offset 0.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TapeLayout {
+    fields: Vec<(String, Field)>,
+}
+
+impl TapeLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a field of the given width right after the previous one.
+    pub fn with_field(self, name: &str, width: usize) -> Self {
+        self.with_gap(name, 0, width)
+    }
+
+    /// Appends a field after leaving the given number of unused cells.
+    pub fn with_gap(mut self, name: &str, gap: usize, width: usize) -> Self {
+        assert!(self.find(name).is_none(), "Field '{}' is declared twice", name);
+        let offset = self.get_width() as isize + gap as isize;
+        self.fields.push((name.to_string(), Field::new(offset, width)));
+        self
+    }
+
+    /// The field with the given name, its offset is relative to the start of the layout.
+    pub fn get_field(&self, name: &str) -> Field {
+        self.find(name).unwrap_or_else(|| panic!("Unknown field '{}'", name))
+    }
+
+    pub fn get_fields(&self) -> impl Iterator<Item = (&str, Field)> {
+        self.fields.iter().map(|(name, field)| (name.as_str(), *field))
+    }
+
+    /// The number of cells covered by the layout.
+    pub fn get_width(&self) -> usize {
+        self.fields.last().map(|(_, field)| field.get_end() as usize).unwrap_or(0)
+    }
+
+    /// Reads a field of the layout placed at the given tape index.
+    pub fn read_field(&self, tape: &impl Tape, origin: usize, name: &str) -> u64 {
+        let field = self.get_field(name);
+        tape.read_unsigned(origin + field.offset as usize, field.width, Endianness::Little)
+    }
+
+    /// Writes a field of the layout placed at the given tape index, the value is truncated to the width of the field.
+    pub fn write_field(&self, tape: &mut impl Tape, origin: usize, name: &str, value: u64) {
+        let field = self.get_field(name);
+        tape.write_unsigned(origin + field.offset as usize, value, field.width, Endianness::Little);
+    }
+
+    fn find(&self, name: &str) -> Option<Field> {
+        self.fields.iter().find(|(field_name, _)| field_name == name).map(|(_, field)| *field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::machine::turing_tape::TuringTape;
+    use super::*;
+
+    #[test]
+    fn test_layout() {
+        let layout = TapeLayout::new()
+            .with_field("x", 4)
+            .with_gap("y", 2, 3)
+            .with_field("flag", 1);
+
+        assert_eq!(layout.get_field("x"), Field::new(0, 4));
+        assert_eq!(layout.get_field("y"), Field::new(6, 3));
+        assert_eq!(layout.get_field("flag"), Field::new(9, 1));
+        assert_eq!(layout.get_width(), 10);
+
+        let mut tape = TuringTape::default();
+        layout.write_field(&mut tape, 3, "x", 0b1011);
+        layout.write_field(&mut tape, 3, "y", 0b110);
+        layout.write_field(&mut tape, 3, "flag", 1);
+        assert_eq!(tape.read_bit_string(0, 13), "000_1101_00_011_1".replace('_', ""));
+        assert_eq!(layout.read_field(&tape, 3, "x"), 0b1011);
+        assert_eq!(layout.read_field(&tape, 3, "y"), 0b110);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown field 'z'")]
+    fn test_unknown_field() {
+        TapeLayout::new().with_field("x", 1).get_field("z");
+    }
+}
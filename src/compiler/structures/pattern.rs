@@ -1,20 +1,188 @@
-#[derive(Debug, Default, Clone, PartialEq)]
-pub struct Pattern(Vec<bool>);
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+
+/// A sequence of cells to write or look for, each either a fixed bit or a wildcard (`None`) standing for any bit.
+///
+/// The string form uses `0`, `1` and `?` for a wildcard, `_` is ignored as a separator. A cell followed by `{n}` is
+/// repeated n times, so `1{3}?0` is the same pattern as `111?0`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Pattern(Vec<Option<bool>>);
 
 impl Pattern {
     pub fn new(pattern: Vec<bool>) -> Self {
+        Self(pattern.into_iter().map(Some).collect())
+    }
+
+    pub fn with_wildcards(pattern: Vec<Option<bool>>) -> Self {
         Self(pattern)
     }
 
-    pub fn get(&self) -> &[bool] {
+    pub fn get(&self) -> &[Option<bool>] {
         &self.0
     }
 
+    /// The bit at the index.
+    ///
+    /// # Panics
+    /// If the cell is a wildcard, see [`Pattern::get_wildcard_at`].
     pub fn get_at(&self, index: usize) -> bool {
+        self.0[index].unwrap_or_else(|| panic!("The cell {} of the pattern is a wildcard", index))
+    }
+
+    /// The bit at the index, or `None` for a wildcard.
+    pub fn get_wildcard_at(&self, index: usize) -> Option<bool> {
         self.0[index]
     }
 
+    /// Whether the cell at the index accepts the bit.
+    pub fn matches_at(&self, index: usize, bit: bool) -> bool {
+        self.0[index].is_none_or(|target_bit| target_bit == bit)
+    }
+
+    /// Whether the pattern occurs at the start of the bits.
+    pub fn matches(&self, bits: &[bool]) -> bool {
+        bits.len() >= self.len() && bits.iter().enumerate().take(self.len()).all(|(i, bit)| self.matches_at(i, *bit))
+    }
+
     pub fn len(&self) -> usize {
         self.0.len()
     }
-}
\ No newline at end of file
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The pattern as it's read when moving to the left.
+    pub fn reversed(&self) -> Self {
+        Self(self.0.iter().rev().copied().collect())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsePatternError(String);
+
+impl Display for ParsePatternError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid pattern: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParsePatternError {}
+
+impl FromStr for Pattern {
+    type Err = ParsePatternError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let mut cells = Vec::new();
+        let mut chars = string.chars();
+        while let Some(char) = chars.next() {
+            match char {
+                '0' => cells.push(Some(false)),
+                '1' => cells.push(Some(true)),
+                '?' => cells.push(None),
+                '_' => {},
+                '{' => {
+                    let mut count = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(char) => count.push(char),
+                            None => return Err(ParsePatternError("unclosed repetition".to_string())),
+                        }
+                    }
+                    let count: usize = count.parse()
+                        .map_err(|_| ParsePatternError(format!("expected a repetition count instead of '{}'", count)))?;
+                    let cell = cells.pop()
+                        .ok_or_else(|| ParsePatternError("a repetition needs a preceding cell".to_string()))?;
+                    cells.extend(std::iter::repeat_n(cell, count));
+                },
+                char => return Err(ParsePatternError(format!("unexpected character '{}'", char))),
+            }
+        }
+        Ok(Self(cells))
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for cell in &self.0 {
+            let char = match cell {
+                Some(false) => '0',
+                Some(true) => '1',
+                None => '?',
+            };
+            write!(f, "{}", char)?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for Pattern {
+    type Error = ParsePatternError;
+
+    fn try_from(string: String) -> Result<Self, Self::Error> {
+        string.parse()
+    }
+}
+
+impl From<Pattern> for String {
+    fn from(pattern: Pattern) -> Self {
+        pattern.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::de::IntoDeserializer;
+    use serde::de::value::{Error, StrDeserializer};
+    use super::*;
+
+    fn deserialize(string: &str) -> Result<Pattern, Error> {
+        let deserializer: StrDeserializer<Error> = string.into_deserializer();
+        Pattern::deserialize(deserializer)
+    }
+
+    #[test]
+    fn test_from_str() {
+        let pattern: Pattern = "1{3}?0".parse().unwrap();
+        assert_eq!(pattern, Pattern::with_wildcards(vec![Some(true), Some(true), Some(true), None, Some(false)]));
+        assert_eq!(pattern.to_string(), "111?0");
+        assert_eq!("11_01".parse::<Pattern>().unwrap(), Pattern::new(vec![true, true, false, true]));
+        assert_eq!("?{2}".parse::<Pattern>().unwrap().to_string(), "??");
+        assert_eq!("1{0}0".parse::<Pattern>().unwrap().to_string(), "0");
+
+        assert!("12".parse::<Pattern>().is_err());
+        assert!("{2}".parse::<Pattern>().is_err());
+        assert!("1{x}".parse::<Pattern>().is_err());
+        assert!("1{2".parse::<Pattern>().is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let pattern: Pattern = "1?0".parse().unwrap();
+        assert!(pattern.matches(&[true, false, false]));
+        assert!(pattern.matches(&[true, true, false, true]));
+        assert!(!pattern.matches(&[true, true, true]));
+        assert!(!pattern.matches(&[true, true]));
+        assert_eq!(pattern.reversed().to_string(), "0?1");
+        assert!(pattern.get_at(0));
+        assert_eq!(pattern.get_wildcard_at(1), None);
+        assert_eq!(pattern.get_wildcard_at(2), Some(false));
+    }
+
+    #[test]
+    #[should_panic(expected = "The cell 1 of the pattern is a wildcard")]
+    fn test_get_at_wildcard() {
+        "1?0".parse::<Pattern>().unwrap().get_at(1);
+    }
+
+    #[test]
+    fn test_serde() {
+        let pattern = deserialize("10?").unwrap();
+        assert_eq!(pattern, Pattern::with_wildcards(vec![Some(true), Some(false), None]));
+        assert_eq!(String::from(pattern), "10?");
+        assert!(deserialize("1x").is_err());
+    }
+}
@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::iter::Peekable;
+use std::str::Chars;
+use std::str::FromStr;
+use crate::compiler::structures::dfa::Dfa;
+
+/// A regular expression over the bits `0` and `1`.
+///
+/// Supported are `0`, `1`, `.` for any bit, grouping with `(...)`, alternation with `|` and the repetitions `*`, `+`,
+/// `?`, `{n}`, `{n,}` and `{n,m}`. This is a subset of the usual syntax, so the same expression can be given to other
+/// regex engines on strings of `0` and `1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Regex(Node);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    /// A single cell, `None` for any bit
+    Bit(Option<bool>),
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat(Box<Node>, usize, Option<usize>),
+}
+
+impl Regex {
+    /// The regex matching the reversed words, used when reading the tape to the left.
+    pub fn reversed(&self) -> Self {
+        Self(reverse(&self.0))
+    }
+
+    /// The DFA deciding whether the bits read form a word of the regex.
+    pub fn to_dfa(&self) -> Dfa {
+        Nfa::new(&self.0).to_dfa(false)
+    }
+
+    /// The DFA accepting as soon as a word of the regex ends, wherever it started.
+    pub fn to_search_dfa(&self) -> Dfa {
+        Nfa::new(&self.0).to_dfa(true)
+    }
+}
+
+fn reverse(node: &Node) -> Node {
+    match node {
+        Node::Bit(bit) => Node::Bit(*bit),
+        Node::Concat(nodes) => Node::Concat(nodes.iter().rev().map(reverse).collect()),
+        Node::Alternate(nodes) => Node::Alternate(nodes.iter().map(reverse).collect()),
+        Node::Repeat(node, min, max) => Node::Repeat(Box::new(reverse(node)), *min, *max),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRegexError(String);
+
+impl Display for ParseRegexError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid regex: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseRegexError {}
+
+impl FromStr for Regex {
+    type Err = ParseRegexError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let mut chars = string.chars().peekable();
+        let node = parse_alternation(&mut chars)?;
+        match chars.next() {
+            None => Ok(Self(node)),
+            Some(char) => Err(ParseRegexError(format!("unexpected '{}'", char))),
+        }
+    }
+}
+
+fn parse_alternation(chars: &mut Peekable<Chars>) -> Result<Node, ParseRegexError> {
+    let mut alternatives = vec![parse_concat(chars)?];
+    while chars.next_if_eq(&'|').is_some() {
+        alternatives.push(parse_concat(chars)?);
+    }
+    Ok(if alternatives.len() == 1 { alternatives.remove(0) } else { Node::Alternate(alternatives) })
+}
+
+fn parse_concat(chars: &mut Peekable<Chars>) -> Result<Node, ParseRegexError> {
+    let mut nodes = Vec::new();
+    while let Some(char) = chars.peek() {
+        let atom = match char {
+            '|' | ')' => break,
+            '0' | '1' | '.' => {
+                let char = chars.next().unwrap();
+                Node::Bit(match char {
+                    '0' => Some(false),
+                    '1' => Some(true),
+                    _ => None,
+                })
+            },
+            '(' => {
+                chars.next();
+                let group = parse_alternation(chars)?;
+                if chars.next() != Some(')') {
+                    return Err(ParseRegexError("unclosed group".to_string()));
+                }
+                group
+            },
+            char => return Err(ParseRegexError(format!("unexpected '{}'", char))),
+        };
+        nodes.push(parse_repetitions(chars, atom)?);
+    }
+    Ok(Node::Concat(nodes))
+}
+
+fn parse_repetitions(chars: &mut Peekable<Chars>, mut node: Node) -> Result<Node, ParseRegexError> {
+    while let Some(char) = chars.next_if(|char| matches!(char, '*' | '+' | '?' | '{')) {
+        let (min, max) = match char {
+            '*' => (0, None),
+            '+' => (1, None),
+            '?' => (0, Some(1)),
+            _ => {
+                let mut bounds = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(char) => bounds.push(char),
+                        None => return Err(ParseRegexError("unclosed repetition".to_string())),
+                    }
+                }
+                parse_bounds(&bounds)?
+            },
+        };
+        node = Node::Repeat(Box::new(node), min, max);
+    }
+    Ok(node)
+}
+
+fn parse_bounds(bounds: &str) -> Result<(usize, Option<usize>), ParseRegexError> {
+    let parse = |count: &str| count.parse::<usize>()
+        .map_err(|_| ParseRegexError(format!("expected a repetition count instead of '{}'", count)));
+    let (min, max) = match bounds.split_once(',') {
+        None => (parse(bounds)?, Some(parse(bounds)?)),
+        Some((min, "")) => (parse(min)?, None),
+        Some((min, max)) => (parse(min)?, Some(parse(max)?)),
+    };
+    if max.is_some_and(|max| max < min) {
+        return Err(ParseRegexError(format!("the repetition {{{}}} is empty", bounds)));
+    }
+    Ok((min, max))
+}
+
+/// A Thompson NFA, state 0 is the start and state 1 accepts.
+struct Nfa {
+    epsilons: Vec<Vec<usize>>,
+    /// Transitions on a bit, `None` for any bit
+    bits: Vec<Vec<(Option<bool>, usize)>>,
+}
+
+impl Nfa {
+    fn new(node: &Node) -> Self {
+        let mut nfa = Self { epsilons: Vec::new(), bits: Vec::new() };
+        let start = nfa.add_state();
+        let accept = nfa.add_state();
+        let end = nfa.add_node(node, start);
+        nfa.epsilons[end].push(accept);
+        nfa
+    }
+
+    fn add_state(&mut self) -> usize {
+        self.epsilons.push(Vec::new());
+        self.bits.push(Vec::new());
+        self.epsilons.len() - 1
+    }
+
+    /// Adds the states of the node after the given state and returns the state where it ends.
+    fn add_node(&mut self, node: &Node, start: usize) -> usize {
+        match node {
+            Node::Bit(bit) => {
+                let end = self.add_state();
+                self.bits[start].push((*bit, end));
+                end
+            },
+            Node::Concat(nodes) => nodes.iter().fold(start, |state, node| self.add_node(node, state)),
+            Node::Alternate(nodes) => {
+                let end = self.add_state();
+                for node in nodes {
+                    let branch = self.add_state();
+                    self.epsilons[start].push(branch);
+                    let branch_end = self.add_node(node, branch);
+                    self.epsilons[branch_end].push(end);
+                }
+                end
+            },
+            Node::Repeat(node, min, max) => {
+                let mut state = start;
+                for _ in 0..*min {
+                    state = self.add_node(node, state);
+                }
+                match max {
+                    None => {
+                        let loop_state = self.add_state();
+                        self.epsilons[state].push(loop_state);
+                        let loop_end = self.add_node(node, loop_state);
+                        self.epsilons[loop_end].push(loop_state);
+                        loop_state
+                    },
+                    Some(max) => {
+                        for _ in *min..*max {
+                            let end = self.add_state();
+                            self.epsilons[state].push(end);
+                            let optional_end = self.add_node(node, state);
+                            self.epsilons[optional_end].push(end);
+                            state = end;
+                        }
+                        state
+                    },
+                }
+            },
+        }
+    }
+
+    fn get_closure(&self, states: impl IntoIterator<Item = usize>) -> Vec<usize> {
+        let mut closure: Vec<usize> = Vec::new();
+        let mut pending: Vec<usize> = states.into_iter().collect();
+        while let Some(state) = pending.pop() {
+            if !closure.contains(&state) {
+                closure.push(state);
+                pending.extend(&self.epsilons[state]);
+            }
+        }
+        closure.sort();
+        closure
+    }
+
+    /// The subset construction, when searching the start is added after every bit so a match can begin anywhere.
+    fn to_dfa(&self, search: bool) -> Dfa {
+        let start = self.get_closure([0]);
+        let mut states: HashMap<Vec<usize>, usize> = HashMap::from([(start.clone(), 0)]);
+        let mut queue = vec![start];
+        let mut transitions = Vec::new();
+
+        while transitions.len() < queue.len() {
+            let subset = queue[transitions.len()].clone();
+            let transition = [false, true].map(|bit| {
+                let mut next: Vec<usize> = subset.iter()
+                    .flat_map(|state| &self.bits[*state])
+                    .filter(|(target_bit, _)| target_bit.is_none_or(|target_bit| target_bit == bit))
+                    .map(|(_, next)| *next)
+                    .collect();
+                if search {
+                    next.push(0);
+                }
+                let next = self.get_closure(next);
+                *states.entry(next.clone()).or_insert_with(|| {
+                    queue.push(next);
+                    queue.len() - 1
+                })
+            });
+            transitions.push(transition);
+        }
+
+        let accepting = queue.iter().map(|subset| subset.contains(&1)).collect();
+        Dfa::new(transitions, accepting)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::to_bits;
+    use super::*;
+
+    fn accepts(regex: &str, bits: &str) -> bool {
+        regex.parse::<Regex>().unwrap().to_dfa().accepts(to_bits(bits))
+    }
+
+    #[test]
+    fn test_to_dfa() {
+        assert!(accepts("10*1", "1001"));
+        assert!(accepts("10*1", "11"));
+        assert!(!accepts("10*1", "1011"));
+        assert!(accepts("(01)+|1{2,3}", "0101"));
+        assert!(accepts("(01)+|1{2,3}", "111"));
+        assert!(!accepts("(01)+|1{2,3}", "1111"));
+        assert!(!accepts("(01)+|1{2,3}", ""));
+        assert!(accepts("1?.{2}", "00"));
+        assert!(accepts("(0|)1", "1"));
+        assert!(accepts("", ""));
+    }
+
+    #[test]
+    fn test_reversed() {
+        let regex: Regex = "1(10)*0{2,}".parse().unwrap();
+        assert!(regex.to_dfa().accepts(to_bits("11000")));
+        assert!(regex.reversed().to_dfa().accepts(to_bits("00011")));
+        assert!(!regex.reversed().to_dfa().accepts(to_bits("11000")));
+    }
+
+    #[test]
+    fn test_to_search_dfa() {
+        let dfa = "101".parse::<Regex>().unwrap().to_search_dfa();
+        let first_match = |bits: &str| to_bits(bits).into_iter()
+            .scan(0, |state, bit| {
+                *state = dfa.get_transition(*state, bit);
+                Some(dfa.is_accepting(*state))
+            })
+            .position(|accepting| accepting);
+        assert_eq!(first_match("0010101"), Some(4));
+        assert_eq!(first_match("1001"), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for regex in ["2", "(01", "01)", "1{2", "1{x}", "1{3,2}", "*"] {
+            assert!(regex.parse::<Regex>().is_err(), "{}", regex);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use crate::compiler::structures::pattern::Pattern;
+
+/// What happens after reading a bit, either the scan continues in another automaton state or a pattern was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanTransition {
+    Continue(usize),
+    Found(usize),
+}
+
+/// A deterministic automaton reading bits until the first occurrence of any of several patterns.
+///
+/// Every state is the set of partial matches which are still alive, so this is the Aho–Corasick automaton of the
+/// patterns (for a single pattern the KMP automaton), which also works for wildcards. State 0 is the start where
+/// nothing was matched yet.
+/// When several patterns end on the same bit the one given first is found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanAutomaton {
+    transitions: Vec<[ScanTransition; 2]>,
+}
+
+/// The partial matches of a state, as pattern index and number of matched bits.
+type PartialMatches = Vec<(usize, usize)>;
+
+impl ScanAutomaton {
+    pub fn new(patterns: &[Pattern]) -> Self {
+        assert!(patterns.iter().all(|pattern| !pattern.is_empty()), "Can't scan for an empty pattern");
+
+        let mut states: HashMap<PartialMatches, usize> = HashMap::from([(Vec::new(), 0)]);
+        let mut queue: Vec<PartialMatches> = vec![Vec::new()];
+        let mut transitions = Vec::new();
+
+        // States are numbered in the order they are discovered, so the queue index is the state
+        while transitions.len() < queue.len() {
+            let partial_matches = queue[transitions.len()].clone();
+            let transition = [false, true].map(|bit| {
+                let mut next: PartialMatches = (0..patterns.len())
+                    .map(|i| (i, 0))
+                    .chain(partial_matches.iter().copied())
+                    .filter(|(i, matched)| patterns[*i].matches_at(*matched, bit))
+                    .map(|(i, matched)| (i, matched + 1))
+                    .collect();
+                if let Some((i, _)) = next.iter().filter(|(i, matched)| *matched == patterns[*i].len()).min() {
+                    return ScanTransition::Found(*i);
+                }
+
+                next.sort();
+                next.dedup();
+                let state = *states.entry(next.clone()).or_insert_with(|| {
+                    queue.push(next);
+                    queue.len() - 1
+                });
+                ScanTransition::Continue(state)
+            });
+            transitions.push(transition);
+        }
+
+        Self { transitions }
+    }
+
+    pub fn get_state_count(&self) -> usize {
+        self.transitions.len()
+    }
+
+    pub fn get_transition(&self, state: usize, bit: bool) -> ScanTransition {
+        self.transitions[state][bit as usize]
+    }
+
+    /// Feeds the bits into the automaton and returns the index of the bit where a pattern was found and which one.
+    pub fn find(&self, bits: impl IntoIterator<Item = bool>) -> Option<(usize, usize)> {
+        let mut state = 0;
+        for (position, bit) in bits.into_iter().enumerate() {
+            match self.get_transition(state, bit) {
+                ScanTransition::Continue(next) => state = next,
+                ScanTransition::Found(pattern) => return Some((position, pattern)),
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::to_bits;
+    use super::*;
+
+    #[test]
+    fn test_single_pattern() {
+        // One state per matched prefix, like the KMP automaton
+        let automaton = ScanAutomaton::new(&["110".parse().unwrap()]);
+        assert_eq!(automaton.get_state_count(), 3);
+        assert_eq!(automaton.find(to_bits("1110")), Some((3, 0)));
+        assert_eq!(automaton.find(to_bits("1011")), None);
+    }
+
+    #[test]
+    fn test_multiple_patterns() {
+        let automaton = ScanAutomaton::new(&["0110".parse().unwrap(), "10".parse().unwrap(), "111".parse().unwrap()]);
+        assert_eq!(automaton.find(to_bits("0010")), Some((3, 1)));
+        assert_eq!(automaton.find(to_bits("00111")), Some((4, 2)));
+        assert_eq!(automaton.find(to_bits("0000")), None);
+
+        // Both end on the same bit, the first given pattern wins
+        assert_eq!(automaton.find(to_bits("0110")), Some((3, 0)));
+        let automaton = ScanAutomaton::new(&["10".parse().unwrap(), "0110".parse().unwrap()]);
+        assert_eq!(automaton.find(to_bits("0110")), Some((3, 0)));
+    }
+}
@@ -1,7 +1,19 @@
 use crate::compiler::layers::base::BaseLayer;
+use crate::compiler::layers::bitwise::BitwiseLayer;
+use crate::compiler::layers::comparison::ComparisonLayer;
+use crate::compiler::layers::counter::CounterLayer;
+use crate::compiler::layers::data_structure::DataStructureLayer;
+use crate::compiler::layers::field::FieldLayer;
+use crate::compiler::layers::integer::IntegerLayer;
+use crate::compiler::layers::layout::LayoutLayer;
+use crate::compiler::layers::fragment::FragmentLayer;
 use crate::compiler::layers::pattern::PatternLayer;
 use crate::compiler::layers::primitive::PrimitiveLayer;
 use crate::compiler::layers::program_builder::ProgramBuilder;
+use crate::compiler::layers::regex::RegexLayer;
+use crate::compiler::layers::region::RegionLayer;
+use crate::compiler::layers::tape_shift::TapeShiftLayer;
+use crate::compiler::structures::layout::TapeLayout;
 use crate::machine::instruction::Instruction;
 use crate::machine::state::State;
 use crate::machine::turing_program::TuringProgram;
@@ -9,24 +21,44 @@ use crate::machine::turing_program::TuringProgram;
 #[derive(Debug, Default)]
 pub struct TuringCompiler {
     current_state: usize,
+    current_label: Option<String>,
     program: TuringProgram,
+    layout: TapeLayout,
+    layout_position: isize,
 }
 
 impl TuringCompiler {
     pub fn get_program(&self) -> TuringProgram {
         self.program.clone()
     }
+
+    /// All states allocated after this call will carry the given label, which is used to group states in diagrams.
+    pub fn set_label(&mut self, label: Option<&str>) {
+        self.current_label = label.map(|label| label.to_string());
+    }
+
+    /// Labels an already allocated state.
+    pub fn label_state(&mut self, state: State, label: &str) {
+        self.program.set_label(state, label);
+    }
 }
 
 impl ProgramBuilder for TuringCompiler {
     fn allocate_state(&mut self) -> State {
-        let new_state = self.current_state;
+        let new_state = State::new(self.current_state);
         self.current_state += 1;
-        State::new(new_state)
+        if let Some(label) = &self.current_label {
+            self.program.set_label(new_state, label);
+        }
+        new_state
     }
     
+    fn get_state_count(&self) -> usize {
+        self.current_state
+    }
+
     fn get_halt_state(&self) -> State {
-        State::new(usize::MAX)
+        State::halt()
     }
 
     fn get_instruction(&self, state: State, read_bit: bool) -> Option<&Instruction> {
@@ -36,8 +68,45 @@ impl ProgramBuilder for TuringCompiler {
     fn add_instruction(&mut self, instruction: Instruction) {
         self.program.add_instruction(instruction);
     }
+
+    fn redirect_state(&mut self, from: State, to: State) {
+        self.program.redirect_state(from, to);
+    }
+
+    fn swap_states(&mut self, a: State, b: State) {
+        self.program.swap_states(a, b);
+    }
 }
 
 impl PrimitiveLayer for TuringCompiler {}
 impl BaseLayer for TuringCompiler {}
-impl PatternLayer for TuringCompiler {}
\ No newline at end of file
+impl PatternLayer for TuringCompiler {}
+impl FragmentLayer for TuringCompiler {}
+impl FieldLayer for TuringCompiler {}
+impl IntegerLayer for TuringCompiler {}
+impl ComparisonLayer for TuringCompiler {}
+impl BitwiseLayer for TuringCompiler {}
+impl RegionLayer for TuringCompiler {}
+impl TapeShiftLayer for TuringCompiler {}
+impl DataStructureLayer for TuringCompiler {}
+impl RegexLayer for TuringCompiler {}
+impl CounterLayer for TuringCompiler {}
+
+impl LayoutLayer for TuringCompiler {
+    fn get_layout(&self) -> &TapeLayout {
+        &self.layout
+    }
+
+    fn set_layout(&mut self, layout: TapeLayout) {
+        self.layout = layout;
+        self.layout_position = 0;
+    }
+
+    fn get_layout_position(&self) -> isize {
+        self.layout_position
+    }
+
+    fn set_layout_position(&mut self, position: isize) {
+        self.layout_position = position;
+    }
+}
\ No newline at end of file
@@ -1,2 +1,4 @@
 pub mod movement;
-pub mod display_style;
\ No newline at end of file
+pub mod display_style;
+pub mod endianness;
+pub mod halt_code;
\ No newline at end of file
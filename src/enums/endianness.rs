@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+/// The order in which the bits of a number are laid out on the tape, from left to right.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    /// The least significant bit comes first.
+    #[default]
+    Little,
+    /// The most significant bit comes first.
+    Big
+}
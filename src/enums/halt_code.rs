@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+/// Which halt state a machine stopped in, every code has a halt state of its own.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HaltCode {
+    #[default]
+    Halt,
+    Accept,
+    Reject,
+    /// Any further outcome a program wants to report
+    Custom(u16),
+}
+
+impl HaltCode {
+    pub fn get_name(&self) -> String {
+        match self {
+            Self::Halt => "HALT".to_string(),
+            Self::Accept => "ACCEPT".to_string(),
+            Self::Reject => "REJECT".to_string(),
+            Self::Custom(code) => format!("HALT_{}", code),
+        }
+    }
+}
@@ -17,4 +17,20 @@ impl Movement {
             Self::Right => "R"
         }
     }
+
+    pub fn get_opposite(&self) -> Self {
+        match self {
+            Self::Stay => Self::Stay,
+            Self::Left => Self::Right,
+            Self::Right => Self::Left
+        }
+    }
+
+    pub fn get_diagram_string(&self) -> &'static str {
+        match self {
+            Self::Stay => "stay",
+            Self::Left => "move L",
+            Self::Right => "move R"
+        }
+    }
 }
\ No newline at end of file
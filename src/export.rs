@@ -0,0 +1,2 @@
+pub mod space_time;
+pub mod html;
\ No newline at end of file
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::machine::execution_trace::ExecutionTrace;
+use crate::machine::state::State;
+
+const TEMPLATE: &str = include_str!("html_template.html");
+
+/// Writes a single self-contained HTML file which lets you scrub through the recorded run step by step.
+pub fn export_html(trace: &ExecutionTrace, path: impl AsRef<Path>) -> io::Result<()> {
+    fs::write(path, render_html(trace))
+}
+
+/// Renders the page written by [`export_html`], the trace is embedded as JSON for the player script.
+pub fn render_html(trace: &ExecutionTrace) -> String {
+    TEMPLATE.replace("/*TRACE_DATA*/", &get_trace_json(trace))
+}
+
+fn get_trace_json(trace: &ExecutionTrace) -> String {
+    let mut states = trace.program.get_states();
+    for step in &trace.steps {
+        if !states.contains(&step.state) {
+            states.push(step.state);
+        }
+    }
+    let state_indices: HashMap<State, usize> = states.iter()
+        .enumerate()
+        .map(|(i, state)| (*state, i))
+        .collect();
+
+    let instructions = trace.program.get_sorted_instructions();
+    let instruction_indices: HashMap<(State, bool), usize> = instructions.iter()
+        .enumerate()
+        .map(|(i, instr)| ((instr.current_state, instr.read_bit), i))
+        .collect();
+
+    let mut json = String::from("{");
+
+    let state_names: Vec<String> = states.iter().map(|state| get_json_string(&state.get_name())).collect();
+    write!(json, "\"states\":[{}],", state_names.join(",")).unwrap();
+    let halting: Vec<String> = states.iter().map(|state| state.is_halt().to_string()).collect();
+    write!(json, "\"halting\":[{}],", halting.join(",")).unwrap();
+
+    let instruction_entries: Vec<String> = instructions.iter()
+        .map(|instr| format!(
+            "{{\"from\":{},\"to\":{},\"read\":{},\"write\":{},\"label\":{},\"formal\":{}}}",
+            state_indices[&instr.current_state],
+            state_indices[&instr.next_state],
+            instr.get_read_bit_number(),
+            instr.get_write_bit_number(),
+            get_json_string(&instr.get_diagram_label()),
+            get_json_string(&instr.get_formal_string())
+        ))
+        .collect();
+    write!(json, "\"instructions\":[{}],", instruction_entries.join(",")).unwrap();
+
+    let initial_cells: String = trace.initial_cells.iter().map(|bit| if *bit { '1' } else { '0' }).collect();
+    write!(json, "\"initialPosition\":{},\"initialCells\":\"{}\",", trace.initial_position, initial_cells).unwrap();
+
+    // Every step as [step, position, state index, instruction index or -1]
+    let steps: Vec<String> = trace.steps.iter()
+        .map(|step| {
+            let instruction = step.instruction
+                .map(|instr| instruction_indices[&(instr.current_state, instr.read_bit)] as i64)
+                .unwrap_or(-1);
+            format!("[{},{},{},{}]", step.step, step.position, state_indices[&step.state], instruction)
+        })
+        .collect();
+    write!(json, "\"steps\":[{}]", steps.join(",")).unwrap();
+
+    json.push('}');
+    json
+}
+
+/// Quotes and escapes a string for JSON, `</` is escaped as well since the JSON ends up in a script tag.
+fn get_json_string(string: &str) -> String {
+    let mut escaped = String::from("\"");
+    for char in string.chars() {
+        match char {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '/' => escaped.push_str("\\/"),
+            '\n' => escaped.push_str("\\n"),
+            char if (char as u32) < 0x20 => write!(escaped, "\\u{:04x}", char as u32).unwrap(),
+            char => escaped.push(char),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::layers::base::BaseLayer;
+    use crate::compiler::layers::primitive::PrimitiveLayer;
+    use crate::compiler::layers::program_builder::ProgramBuilder;
+    use crate::compiler::turing_compiler::TuringCompiler;
+    use crate::enums::movement::Movement;
+    use crate::machine::turing_machine::TuringMachine;
+    use crate::machine::tape::Tape;
+    use crate::machine::turing_tape::TuringTape;
+    use super::*;
+
+    #[test]
+    fn test_render_html() {
+        let mut compiler = TuringCompiler::default();
+        let scan_state = compiler.allocate_state();
+        let done = compiler.halt(None);
+        compiler.scan_single(true, Movement::Right, Movement::Stay, Some(scan_state), Some(done));
+
+        let mut tape = TuringTape::default();
+        tape.set(2);
+        let tm = TuringMachine::default().with_program(compiler.get_program()).with_tape(tape);
+        let trace = ExecutionTrace::record(&tm, 0..100);
+
+        let html = render_html(&trace);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(!html.contains("/*TRACE_DATA*/"));
+        assert!(html.contains("\"states\":[\"0\",\"1\",\"HALT\"]"));
+        assert!(html.contains("\"formal\":\"(q=0, σ=0) => (q'=0, σ'=0, D=R)\""));
+        assert!(html.contains("\"initialPosition\":0,\"initialCells\":\"00100000\""));
+        assert!(html.contains("\"steps\":[[0,0,0,0],[1,1,0,0],[2,2,0,1],[3,2,1,3],[4,2,2,-1]]"));
+    }
+
+    #[test]
+    fn test_get_json_string() {
+        assert_eq!(get_json_string("a\"b\\c</script>\n"), "\"a\\\"b\\\\c<\\/script>\\n\"");
+    }
+}
@@ -0,0 +1,282 @@
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use crate::machine::execution_trace::ExecutionTrace;
+use crate::machine::state::State;
+use crate::machine::tape::Tape;
+use crate::machine::turing_machine::TuringMachine;
+
+const COLOR_BLANK: [u8; 3] = [255, 255, 255];
+const COLOR_MARKED: [u8; 3] = [0, 0, 0];
+const COLOR_HALT: [u8; 3] = [220, 20, 60];
+
+/// A raster image of a run, one row per step and one pixel column per tape cell.
+/// Unmarked cells are white, marked cells are black and the head is colored by the current state.
+#[derive(Debug, Clone)]
+pub struct SpaceTimeDiagram {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<[u8; 3]>,
+}
+
+impl SpaceTimeDiagram {
+    pub fn from_trace(trace: &ExecutionTrace) -> Self {
+        let (min, _) = trace.get_bounds();
+        let rows = trace.get_cells();
+        let width = rows.first().map(|row| row.len()).unwrap_or(0);
+
+        let mut pixels = Vec::with_capacity(width * rows.len());
+        for (row, step) in rows.iter().zip(&trace.steps) {
+            let head = (step.position - min) as usize;
+            for (i, bit) in row.iter().enumerate() {
+                let color = if i == head {
+                    get_state_color(step.state, *bit)
+                } else if *bit {
+                    COLOR_MARKED
+                } else {
+                    COLOR_BLANK
+                };
+                pixels.push(color);
+            }
+        }
+
+        Self {
+            width,
+            height: rows.len(),
+            pixels,
+        }
+    }
+
+    /// Records the given step range of a copy of the machine, see [`ExecutionTrace::record`].
+    pub fn from_machine<T: Tape>(tm: &TuringMachine<T>, steps: Range<usize>) -> Self {
+        Self::from_trace(&ExecutionTrace::record(tm, steps))
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Binary PPM (P6), readable by most image viewers.
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut ppm = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        ppm.extend(self.pixels.iter().flatten());
+        ppm
+    }
+
+    /// SVG with one rectangle per horizontal run of equally colored pixels.
+    pub fn to_svg(&self) -> String {
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\" shape-rendering=\"crispEdges\">",
+            self.width,
+            self.height
+        ).unwrap();
+        writeln!(svg, "<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>", self.width, self.height, get_hex_color(COLOR_BLANK)).unwrap();
+
+        for y in 0..self.height {
+            let mut x = 0;
+            while x < self.width {
+                let color = self.get_pixel(x, y);
+                let run_start = x;
+                while x < self.width && self.get_pixel(x, y) == color {
+                    x += 1;
+                }
+                if color != COLOR_BLANK {
+                    writeln!(
+                        svg,
+                        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"1\" fill=\"{}\"/>",
+                        run_start,
+                        y,
+                        x - run_start,
+                        get_hex_color(color)
+                    ).unwrap();
+                }
+            }
+        }
+
+        svg.push_str("</svg>");
+        svg
+    }
+
+    /// PNG without compression, so it needs no extra dependencies.
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut raw = Vec::with_capacity((self.width * 3 + 1) * self.height);
+        for row in self.pixels.chunks(self.width.max(1)).take(self.height) {
+            raw.push(0); // Filter type: none
+            raw.extend(row.iter().flatten());
+        }
+
+        let mut header = Vec::with_capacity(13);
+        header.extend((self.width as u32).to_be_bytes());
+        header.extend((self.height as u32).to_be_bytes());
+        header.extend([8, 2, 0, 0, 0]); // 8 bit depth, truecolor, no interlacing
+
+        let mut png = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+        write_png_chunk(&mut png, b"IHDR", &header);
+        write_png_chunk(&mut png, b"IDAT", &zlib_store(&raw));
+        write_png_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    /// Writes the diagram to the given path, the format is chosen by the file extension (png, ppm or svg).
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let extension = path.extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase());
+        match extension.as_deref() {
+            Some("png") => fs::write(path, self.to_png()),
+            Some("ppm") => fs::write(path, self.to_ppm()),
+            Some("svg") => fs::write(path, self.to_svg()),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "Expected a .png, .ppm or .svg file")),
+        }
+    }
+}
+
+/// A distinct color per state, the head is drawn darker when it's on a marked cell.
+pub fn get_state_color(state: State, bit: bool) -> [u8; 3] {
+    let color = if state.is_halt() {
+        COLOR_HALT
+    } else {
+        // Golden ratio steps spread neighbouring states over the color wheel
+        let hue = (state.get() as f64 * 0.618_033_988_75).fract() * 6.0;
+        let x = 1.0 - (hue % 2.0 - 1.0).abs();
+        let (r, g, b) = match hue as usize {
+            0 => (1.0, x, 0.0),
+            1 => (x, 1.0, 0.0),
+            2 => (0.0, 1.0, x),
+            3 => (0.0, x, 1.0),
+            4 => (x, 0.0, 1.0),
+            _ => (1.0, 0.0, x),
+        };
+        [(r * 200.0) as u8 + 40, (g * 200.0) as u8 + 40, (b * 200.0) as u8 + 40]
+    };
+
+    if bit {
+        color.map(|channel| channel / 2)
+    } else {
+        color
+    }
+}
+
+fn get_hex_color(color: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+fn write_png_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let chunk_start = png.len();
+    png.extend(chunk_type);
+    png.extend(data);
+    let crc = crc32(&png[chunk_start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+/// Wraps the data into a zlib stream of uncompressed (stored) deflate blocks.
+fn zlib_store(data: &[u8]) -> Vec<u8> {
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = data.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend([1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none();
+        let length = block.len() as u16;
+        zlib.push(is_final as u8);
+        zlib.extend(length.to_le_bytes());
+        zlib.extend((!length).to_le_bytes());
+        zlib.extend(block);
+    }
+    zlib.extend(adler32(data).to_be_bytes());
+    zlib
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::layers::base::BaseLayer;
+    use crate::compiler::layers::primitive::PrimitiveLayer;
+    use crate::compiler::layers::program_builder::ProgramBuilder;
+    use crate::compiler::turing_compiler::TuringCompiler;
+    use crate::enums::movement::Movement;
+    use crate::machine::tape::Tape;
+    use crate::machine::turing_tape::TuringTape;
+    use super::*;
+
+    fn build_diagram() -> SpaceTimeDiagram {
+        let mut compiler = TuringCompiler::default();
+        let scan_state = compiler.allocate_state();
+        let done = compiler.halt(None);
+        compiler.scan_single(true, Movement::Right, Movement::Stay, Some(scan_state), Some(done));
+
+        let mut tape = TuringTape::default();
+        tape.set(3);
+        let tm = TuringMachine::default().with_program(compiler.get_program()).with_tape(tape);
+        SpaceTimeDiagram::from_machine(&tm, 0..100)
+    }
+
+    #[test]
+    fn test_from_machine() {
+        let diagram = build_diagram();
+        // 4 scan steps, the halt instruction and the halting configuration
+        assert_eq!(diagram.height, 6);
+        assert_eq!(diagram.width, 8);
+
+        assert_eq!(diagram.get_pixel(0, 0), get_state_color(State::new(0), false));
+        assert_eq!(diagram.get_pixel(1, 0), COLOR_BLANK);
+        assert_eq!(diagram.get_pixel(3, 0), COLOR_MARKED);
+        assert_eq!(diagram.get_pixel(2, 2), get_state_color(State::new(0), false));
+        assert_eq!(diagram.get_pixel(3, 3), get_state_color(State::new(0), true));
+        assert_eq!(diagram.get_pixel(3, 5), get_state_color(State::halt(), true));
+    }
+
+    #[test]
+    fn test_formats() {
+        let diagram = build_diagram();
+
+        let ppm = diagram.to_ppm();
+        let header = b"P6\n8 6\n255\n";
+        assert_eq!(&ppm[..header.len()], header);
+        assert_eq!(ppm.len(), header.len() + 8 * 6 * 3);
+
+        let png = diagram.to_png();
+        assert_eq!(&png[..8], &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 8, 0, 0, 0, 6]);
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+
+        let svg = diagram.to_svg();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("<rect x=\"3\" y=\"0\" width=\"1\" height=\"1\" fill=\"#000000\"/>"));
+        assert!(svg.ends_with("</svg>"));
+    }
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+}
@@ -0,0 +1,7 @@
+pub mod enums;
+pub mod machine;
+pub mod compiler;
+pub mod export;
+
+#[cfg(test)]
+mod test_utils;
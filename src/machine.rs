@@ -2,4 +2,8 @@ pub mod instruction;
 pub mod turing_machine;
 pub mod turing_program;
 pub mod turing_tape;
-pub mod state;
\ No newline at end of file
+pub mod state;
+pub mod execution_trace;
+pub mod tape;
+pub mod sparse_tape;
+pub mod compute;
\ No newline at end of file
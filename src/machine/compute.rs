@@ -0,0 +1,180 @@
+use std::fmt::{Display, Formatter};
+use crate::enums::endianness::Endianness;
+use crate::enums::halt_code::HaltCode;
+use crate::machine::tape::Tape;
+use crate::machine::turing_machine::TuringMachine;
+use crate::machine::turing_program::TuringProgram;
+
+/// How many steps [`compute`] runs a program before giving up.
+pub const DEFAULT_STEP_BUDGET: usize = 1_000_000;
+
+/// How a value is laid out on the tape when passed to a program and read back after it halted.
+/// The input is written to a fresh tape starting at cell 0, where the head starts. The output is read starting at the
+/// cell the head halted on.
+pub trait Encoding {
+    type Output;
+
+    fn write_input(&self, tape: &mut impl Tape);
+    fn read_output(tape: &impl Tape, head: usize) -> Self::Output;
+}
+
+/// Binary numbers, 64 bits least significant bit first like the fields of the
+/// [`IntegerLayer`](crate::compiler::layers::integer::IntegerLayer).
+impl Encoding for u64 {
+    type Output = u64;
+
+    fn write_input(&self, tape: &mut impl Tape) {
+        tape.write_unsigned(0, *self, 64, Endianness::Little);
+    }
+
+    fn read_output(tape: &impl Tape, head: usize) -> u64 {
+        tape.read_unsigned(head, 64, Endianness::Little)
+    }
+}
+
+/// Unary numbers, as many 1s as the value. The output ends at the first 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unary(pub usize);
+
+impl Encoding for Unary {
+    type Output = Unary;
+
+    fn write_input(&self, tape: &mut impl Tape) {
+        tape.write_unary(0, self.0);
+    }
+
+    fn read_output(tape: &impl Tape, head: usize) -> Unary {
+        Unary(tape.read_unary(head))
+    }
+}
+
+/// Strings of `0`s and `1`s. The output is the rest of the tape from the head on, without the trailing 0s.
+impl Encoding for &str {
+    type Output = String;
+
+    fn write_input(&self, tape: &mut impl Tape) {
+        tape.write_bit_string(0, self);
+    }
+
+    fn read_output(tape: &impl Tape, head: usize) -> String {
+        let bits = tape.read_bit_string(head, tape.get_bit_length().saturating_sub(head));
+        bits.trim_end_matches('0').to_string()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ComputeError {
+    /// The program didn't halt within the step budget
+    OutOfSteps(usize),
+    /// The program halted with another code than [`HaltCode::Halt`]
+    Halted(HaltCode),
+}
+
+impl Display for ComputeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OutOfSteps(step_budget) => write!(f, "The program didn't halt within {} steps", step_budget),
+            Self::Halted(code) => write!(f, "The program halted with {} instead of computing an output", code.get_name()),
+        }
+    }
+}
+
+impl std::error::Error for ComputeError {}
+
+/// Runs the program on the input and decodes its output, both in the same [`Encoding`].
+///
+/// # Examples
+/// ```ignore
+/// let mut compiler = TuringCompiler::default();
+/// let start_state = compiler.allocate_state();
+/// compiler.increment_n(64, Some(start_state), Some(State::halt()));
+/// assert_eq!(compute(&compiler.get_program(), 41u64), 42);
+/// ```
+///
+/// # Panics
+/// If the program doesn't halt within [`DEFAULT_STEP_BUDGET`] steps or halts with another code than
+/// [`HaltCode::Halt`], see [`try_compute`].
+pub fn compute<E: Encoding>(program: &TuringProgram, input: E) -> E::Output {
+    try_compute(program, input, DEFAULT_STEP_BUDGET).unwrap_or_else(|error| panic!("{}", error))
+}
+
+/// Runs the program on the input for at most `step_budget` steps and decodes its output.
+pub fn try_compute<E: Encoding>(program: &TuringProgram, input: E, step_budget: usize) -> Result<E::Output, ComputeError> {
+    let mut tm = TuringMachine::default().with_program(program.clone());
+    input.write_input(&mut tm.tape);
+
+    match tm.run_program_with_budget(step_budget) {
+        None => Err(ComputeError::OutOfSteps(step_budget)),
+        Some(HaltCode::Halt) => Ok(E::read_output(&tm.tape, tm.head)),
+        Some(code) => Err(ComputeError::Halted(code)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::layers::base::BaseLayer;
+    use crate::compiler::layers::integer::IntegerLayer;
+    use crate::compiler::layers::primitive::PrimitiveLayer;
+    use crate::compiler::layers::program_builder::ProgramBuilder;
+    use crate::compiler::turing_compiler::TuringCompiler;
+    use crate::enums::movement::Movement;
+    use crate::machine::state::State;
+    use super::*;
+
+    #[test]
+    fn test_compute_binary() {
+        let mut compiler = TuringCompiler::default();
+        let start_state = compiler.allocate_state();
+        compiler.increment_n(64, Some(start_state), Some(State::halt()));
+        let inc = compiler.get_program();
+
+        assert_eq!(compute(&inc, 41u64), 42);
+        assert_eq!(compute(&inc, 0u64), 1);
+        assert_eq!(compute(&inc, u64::MAX), 0);
+    }
+
+    #[test]
+    fn test_compute_unary() {
+        // Appends a 1 and returns to the start of the number
+        let mut compiler = TuringCompiler::default();
+        let start_state = compiler.allocate_state();
+        let (_, mark_state) = compiler.scan_single(false, Movement::Right, Movement::Stay, Some(start_state), None);
+        let (_, return_state) = compiler.mark(Some(mark_state), None);
+        compiler.scan_single(false, Movement::Left, Movement::Right, Some(return_state), Some(State::halt()));
+        let inc = compiler.get_program();
+
+        for value in 0..5 {
+            assert_eq!(compute(&inc, Unary(value)), Unary(value + 1));
+        }
+    }
+
+    #[test]
+    fn test_compute_bits() {
+        let mut compiler = TuringCompiler::default();
+        let start_state = compiler.allocate_state();
+        let (_, unmark_state) = compiler.mark_and_move_right(Some(start_state), None);
+        compiler.unmark_and_move_left(Some(unmark_state), Some(State::halt()));
+        let program = compiler.get_program();
+
+        // Trailing 0s can't be told apart from the blank tape, so they are dropped
+        assert_eq!(compute(&program, "0110"), "101");
+        assert_eq!(compute(&program, "0111"), "1011");
+        assert_eq!(compute(&program, "01"), "1");
+        assert_eq!(compute(&program, ""), "1");
+    }
+
+    #[test]
+    fn test_compute_errors() {
+        let mut compiler = TuringCompiler::default();
+        let start_state = compiler.allocate_state();
+        compiler.scan_single(true, Movement::Right, Movement::Stay, Some(start_state), Some(State::halt()));
+        let scan = compiler.get_program();
+        assert_eq!(try_compute(&scan, "0001", 4), Ok("1".to_string()));
+        assert_eq!(try_compute(&scan, "0001", 3), Err(ComputeError::OutOfSteps(3)));
+        assert_eq!(try_compute(&scan, "", 100), Err(ComputeError::OutOfSteps(100)));
+
+        let mut compiler = TuringCompiler::default();
+        compiler.reject(None);
+        assert_eq!(try_compute(&compiler.get_program(), 1u64, 100), Err(ComputeError::Halted(HaltCode::Reject)));
+    }
+}
@@ -0,0 +1,143 @@
+use std::ops::Range;
+use serde::{Deserialize, Serialize};
+use crate::machine::instruction::Instruction;
+use crate::machine::state::State;
+use crate::machine::tape::Tape;
+use crate::machine::turing_machine::TuringMachine;
+use crate::machine::turing_program::TuringProgram;
+
+/// A single configuration of the machine during a run.
+#[derive(Debug, Serialize, Deserialize, Copy, Clone)]
+pub struct TraceStep {
+    /// The index of the step, how many instructions were executed before this configuration.
+    pub step: usize,
+    /// The head position relative to the cell the tape started at, see [`TuringMachine::get_position`].
+    pub position: i64,
+    pub state: State,
+    /// The instruction applied in this configuration, `None` if the machine halted here.
+    pub instruction: Option<Instruction>,
+}
+
+/// A recorded run of a machine, stored as the initial tape plus the applied instructions.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct ExecutionTrace {
+    pub program: TuringProgram,
+    /// The tape cells at the first recorded step, the first cell is at `initial_position`.
+    pub initial_cells: Vec<bool>,
+    pub initial_position: i64,
+    pub steps: Vec<TraceStep>,
+}
+
+impl ExecutionTrace {
+    /// Runs a copy of the given machine and records the configurations of all steps in the given range.
+    /// Recording stops early if the machine halts, the halting configuration is included.
+    /// If the machine halts before the range starts, no steps are recorded.
+    pub fn record<T: Tape>(tm: &TuringMachine<T>, steps: Range<usize>) -> Self {
+        let mut tm = tm.clone();
+        tm.debug_mode = false;
+
+        let halted_before = (0..steps.start).any(|_| !tm.program_step());
+        let steps = if halted_before { 0..0 } else { steps };
+
+        let origin = tm.tape.get_origin() as i64;
+        let mut trace = Self {
+            program: tm.program.clone(),
+            initial_cells: (0..tm.tape.get_bit_length()).map(|i| tm.tape.read(i)).collect(),
+            initial_position: -origin,
+            steps: Vec::new(),
+        };
+
+        for step in steps {
+            let read_bit = tm.read();
+            let instruction = tm.program.get(tm.state, read_bit).copied();
+            trace.steps.push(TraceStep {
+                step,
+                position: tm.get_position(),
+                state: tm.state,
+                instruction,
+            });
+
+            if instruction.is_none() || !tm.program_step() {
+                break;
+            }
+        }
+
+        trace
+    }
+
+    /// The leftmost and rightmost position which is either part of the initial tape or visited by the head.
+    pub fn get_bounds(&self) -> (i64, i64) {
+        let mut min = self.initial_position;
+        let mut max = self.initial_position + self.initial_cells.len() as i64 - 1;
+        for step in &self.steps {
+            min = min.min(step.position);
+            max = max.max(step.position);
+        }
+        (min, max.max(min))
+    }
+
+    /// The tape cells of every recorded configuration (before its instruction is applied), spanning [`Self::get_bounds`].
+    pub fn get_cells(&self) -> Vec<Vec<bool>> {
+        let (min, max) = self.get_bounds();
+        let mut cells = vec![false; (max - min + 1) as usize];
+        for (i, bit) in self.initial_cells.iter().enumerate() {
+            cells[(self.initial_position - min) as usize + i] = *bit;
+        }
+
+        let mut rows = Vec::with_capacity(self.steps.len());
+        for step in &self.steps {
+            rows.push(cells.clone());
+            if let Some(instruction) = step.instruction {
+                cells[(step.position - min) as usize] = instruction.write_bit;
+            }
+        }
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::layers::base::BaseLayer;
+    use crate::compiler::layers::primitive::PrimitiveLayer;
+    use crate::compiler::layers::program_builder::ProgramBuilder;
+    use crate::compiler::turing_compiler::TuringCompiler;
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let mut compiler = TuringCompiler::default();
+        let start_state = compiler.allocate_state();
+        let move_state = compiler.allocate_state();
+        let done = compiler.halt(None);
+
+        compiler.mark(Some(start_state), Some(move_state));
+        compiler.move_left_x(2, Some(move_state), Some(done));
+
+        let tm = TuringMachine::default().with_program(compiler.get_program());
+        let trace = ExecutionTrace::record(&tm, 0..100);
+
+        let positions: Vec<i64> = trace.steps.iter().map(|step| step.position).collect();
+        assert_eq!(positions, vec![0, 0, -1, -2, -2]);
+        assert!(trace.steps[3].instruction.is_some());
+        assert_eq!(trace.get_bounds(), (-2, 0));
+
+        let cells = trace.get_cells();
+        assert_eq!(cells.len(), 5);
+        assert!(!cells[0][2]);
+        assert!(cells[1][2]);
+        assert!(cells[3][2]);
+
+        // The halting configuration is recorded without an instruction
+        let trace = ExecutionTrace::record(&tm, 2..100);
+        assert_eq!(trace.steps.len(), 3);
+        assert_eq!(trace.steps[0].step, 2);
+        assert_eq!(trace.initial_position, -8);
+        assert!(trace.initial_cells[8]);
+        assert!(trace.steps[2].state.is_halt());
+        assert!(trace.steps[2].instruction.is_none());
+
+        // The halting configuration is step 4, so a later range has nothing to record
+        assert_eq!(ExecutionTrace::record(&tm, 4..100).steps.len(), 1);
+        assert!(ExecutionTrace::record(&tm, 5..100).steps.is_empty());
+    }
+}
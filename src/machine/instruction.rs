@@ -58,4 +58,14 @@ impl Instruction {
             self.movement.get_code_string()
         )
     }
+
+    /// The transition label used in state diagrams, e.g. `read 0→write 1, move R`.
+    pub fn get_diagram_label(&self) -> String {
+        format!(
+            "read {}→write {}, {}",
+            self.get_read_bit_number(),
+            self.get_write_bit_number(),
+            self.movement.get_diagram_string()
+        )
+    }
 }
\ No newline at end of file
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::mem::size_of;
+use serde::{Deserialize, Serialize};
+use crate::machine::tape::Tape;
+
+const CHUNK_WORDS: usize = 8;
+const CHUNK_BITS: usize = CHUNK_WORDS * 64;
+
+type Chunk = [u64; CHUNK_WORDS];
+
+/// A tape backend which only stores chunks of 512 cells that were actually written to.
+/// Jumping far away or growing to the left is free, only the bookkeeping of the dense tape is mirrored.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct SparseTape {
+    /// Chunks keyed by their index relative to the original cell 0, so growing to the left never moves data.
+    chunks: HashMap<i64, Chunk>,
+    origin: usize,
+    bit_length: usize,
+}
+
+impl SparseTape {
+    /// The chunk key and the bit position within the chunk.
+    fn locate(&self, bit_index: usize) -> (i64, usize) {
+        let position = bit_index as i64 - self.origin as i64;
+        (position.div_euclid(CHUNK_BITS as i64), position.rem_euclid(CHUNK_BITS as i64) as usize)
+    }
+
+    pub fn get_chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+}
+
+impl Tape for SparseTape {
+    fn read(&self, bit_index: usize) -> bool {
+        let (key, position) = self.locate(bit_index);
+        match self.chunks.get(&key) {
+            Some(chunk) => chunk[position / 64] & (1 << (position % 64)) != 0,
+            None => false,
+        }
+    }
+
+    fn set(&mut self, bit_index: usize) {
+        self.allocate_till_bit_index(bit_index);
+        let (key, position) = self.locate(bit_index);
+        let chunk = self.chunks.entry(key).or_insert([0; CHUNK_WORDS]);
+        chunk[position / 64] |= 1 << (position % 64);
+    }
+
+    fn unset(&mut self, bit_index: usize) {
+        self.allocate_till_bit_index(bit_index);
+        let (key, position) = self.locate(bit_index);
+        // Unallocated chunks are blank already
+        if let Some(chunk) = self.chunks.get_mut(&key) {
+            chunk[position / 64] &= !(1 << (position % 64));
+        }
+    }
+
+    fn allocate_till_bit_index(&mut self, bit_index: usize) {
+        self.bit_length = self.bit_length.max((bit_index / 8 + 1) * 8);
+    }
+
+    fn allocate_left(&mut self, size: usize) {
+        self.origin += size * 8;
+        self.bit_length += size * 8;
+    }
+
+    fn get_bit_length(&self) -> usize {
+        self.bit_length
+    }
+
+    fn get_origin(&self) -> usize {
+        self.origin
+    }
+
+    fn get_memory_usage(&self) -> usize {
+        self.chunks.capacity() * (size_of::<i64>() + size_of::<Chunk>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::machine::turing_tape::TuringTape;
+    use super::*;
+
+    #[test]
+    fn test_matches_dense_tape() {
+        let mut sparse = SparseTape::default();
+        let mut dense = TuringTape::default();
+
+        for tape_index in [3, 700, 511, 512, 0, 1030] {
+            sparse.set(tape_index);
+            dense.set(tape_index);
+        }
+        sparse.unset(700);
+        dense.unset(700);
+        sparse.allocate_left(70);
+        dense.allocate_left(70);
+        sparse.set(0);
+        dense.set(0);
+        sparse.unset(5000);
+        dense.unset(5000);
+
+        assert_eq!(sparse.get_origin(), dense.get_origin());
+        assert_eq!(sparse.get_bit_length(), dense.get_bit_length());
+        for i in 0..dense.get_bit_length() + 100 {
+            assert_eq!(sparse.read(i), dense.read(i), "Mismatch at bit {}", i);
+        }
+    }
+
+    #[test]
+    fn test_far_jumps_stay_small() {
+        let mut tape = SparseTape::default();
+        tape.set(100_000_000);
+        tape.allocate_left(1_000_000);
+        tape.set(0);
+        tape.unset(50_000_000);
+
+        assert!(tape.read(0));
+        assert!(tape.read(108_000_000));
+        assert_eq!(tape.get_chunk_count(), 2);
+    }
+}
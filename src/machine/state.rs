@@ -1,4 +1,8 @@
 use serde::{Deserialize, Serialize};
+use crate::enums::halt_code::HaltCode;
+
+/// The halt states take up the top of the state numbers, one per halt code.
+const HALT_STATE_COUNT: usize = 3 + u16::MAX as usize + 1;
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct State(usize);
@@ -11,4 +15,45 @@ impl State {
     pub fn get(&self) -> usize {
         self.0
     }
+
+    /// The state which stops the machine, the biggest representable state.
+    pub fn halt() -> Self {
+        State(usize::MAX)
+    }
+
+    /// The state which stops the machine with the given code, counting down from [`State::halt`].
+    pub fn halt_with(code: HaltCode) -> Self {
+        let index = match code {
+            HaltCode::Halt => 0,
+            HaltCode::Accept => 1,
+            HaltCode::Reject => 2,
+            HaltCode::Custom(code) => 3 + code as usize,
+        };
+        State(usize::MAX - index)
+    }
+
+    pub fn is_halt(&self) -> bool {
+        self.0 > usize::MAX - HALT_STATE_COUNT
+    }
+
+    /// The code of a halt state, `None` for all other states.
+    pub fn get_halt_code(&self) -> Option<HaltCode> {
+        if !self.is_halt() {
+            return None;
+        }
+        Some(match usize::MAX - self.0 {
+            0 => HaltCode::Halt,
+            1 => HaltCode::Accept,
+            2 => HaltCode::Reject,
+            index => HaltCode::Custom((index - 3) as u16),
+        })
+    }
+
+    /// The number of the state, or the name of the halt code for halt states.
+    pub fn get_name(&self) -> String {
+        match self.get_halt_code() {
+            Some(code) => code.get_name(),
+            None => self.0.to_string(),
+        }
+    }
 }
\ No newline at end of file
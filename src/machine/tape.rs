@@ -0,0 +1,179 @@
+use std::fmt::Debug;
+use std::ops::Range;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::enums::endianness::Endianness;
+
+/// The storage backend of a turing machine's tape.
+/// Bit indices start at the leftmost allocated cell, allocating to the left shifts all indices to the right.
+pub trait Tape: Debug + Default + Clone + Serialize + DeserializeOwned {
+    /// Reads the bit without allocating, cells which were never allocated are blank.
+    fn read(&self, bit_index: usize) -> bool;
+    fn set(&mut self, bit_index: usize);
+    fn unset(&mut self, bit_index: usize);
+    /// Makes sure the given bit index is part of the tape.
+    fn allocate_till_bit_index(&mut self, bit_index: usize);
+    /// Prepends the given amount of bytes (8 cells each) to the tape.
+    fn allocate_left(&mut self, size: usize);
+    /// The amount of currently allocated bits.
+    fn get_bit_length(&self) -> usize;
+    /// The current bit index of the cell which was at index 0 before anything was allocated to the left.
+    fn get_origin(&self) -> usize;
+    /// The approximate amount of heap memory used by the tape in bytes.
+    fn get_memory_usage(&self) -> usize;
+
+    /// Creates a tape from a string of `0`s and `1`s starting at bit index 0, `_` can be used as a separator.
+    ///
+    /// # Panics
+    /// If the string contains any other character.
+    fn from_bit_string(bits: &str) -> Self {
+        let mut tape = Self::default();
+        tape.write_bit_string(0, bits);
+        tape
+    }
+
+    /// Creates a tape from raw bytes, every byte is laid out least significant bit first.
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let mut tape = Self::default();
+        tape.write_bytes(0, bytes);
+        tape
+    }
+
+    /// Creates a tape with the binary representation of the value in `width` bits at the given offset.
+    fn from_unsigned(value: u64, width: usize, endianness: Endianness, offset: usize) -> Self {
+        let mut tape = Self::default();
+        tape.write_unsigned(offset, value, width, endianness);
+        tape
+    }
+
+    /// Creates a tape with the unary representation of the value (`value` consecutive 1s) at the given offset.
+    fn from_unary(value: usize, offset: usize) -> Self {
+        let mut tape = Self::default();
+        tape.write_unary(offset, value);
+        tape
+    }
+
+    fn write(&mut self, bit_index: usize, bit: bool) {
+        if bit {
+            self.set(bit_index);
+        } else {
+            self.unset(bit_index);
+        }
+    }
+
+    /// Writes a string of `0`s and `1`s starting at the offset, `_` can be used as a separator.
+    ///
+    /// # Panics
+    /// If the string contains any other character.
+    fn write_bit_string(&mut self, offset: usize, bits: &str) {
+        let bits = bits.chars().filter(|char| *char != '_');
+        for (i, char) in bits.enumerate() {
+            match char {
+                '0' => self.unset(offset + i),
+                '1' => self.set(offset + i),
+                _ => panic!("Invalid character '{}' in bit string, expected '0', '1' or '_'", char),
+            }
+        }
+    }
+
+    fn write_bytes(&mut self, offset: usize, bytes: &[u8]) {
+        for (i, byte) in bytes.iter().enumerate() {
+            for bit in 0..8 {
+                self.write(offset + i * 8 + bit, byte & (1 << bit) != 0);
+            }
+        }
+    }
+
+    /// Writes the lowest `width` bits of the value starting at the offset.
+    fn write_unsigned(&mut self, offset: usize, value: u64, width: usize, endianness: Endianness) {
+        assert!(width <= 64, "Unsigned values are limited to 64 bits, got a width of {}", width);
+        for i in 0..width {
+            let bit_index = match endianness {
+                Endianness::Little => offset + i,
+                Endianness::Big => offset + width - i - 1,
+            };
+            self.write(bit_index, value & (1 << i) != 0);
+        }
+    }
+
+    /// Writes `value` consecutive 1s followed by a terminating 0.
+    fn write_unary(&mut self, offset: usize, value: usize) {
+        for i in 0..value {
+            self.set(offset + i);
+        }
+        self.unset(offset + value);
+    }
+
+    /// Reads `length` bits starting at the offset as a string of `0`s and `1`s.
+    fn read_bit_string(&self, offset: usize, length: usize) -> String {
+        (offset..offset + length)
+            .map(|i| if self.read(i) { '1' } else { '0' })
+            .collect()
+    }
+
+    fn read_bytes(&self, offset: usize, length: usize) -> Vec<u8> {
+        (0..length)
+            .map(|i| (0..8).fold(0u8, |byte, bit| byte | ((self.read(offset + i * 8 + bit) as u8) << bit)))
+            .collect()
+    }
+
+    /// Reads a `width` bit unsigned value starting at the offset.
+    fn read_unsigned(&self, offset: usize, width: usize, endianness: Endianness) -> u64 {
+        assert!(width <= 64, "Unsigned values are limited to 64 bits, got a width of {}", width);
+        let mut value = 0;
+        for i in 0..width {
+            let bit_index = match endianness {
+                Endianness::Little => offset + i,
+                Endianness::Big => offset + width - i - 1,
+            };
+            if self.read(bit_index) {
+                value |= 1 << i;
+            }
+        }
+        value
+    }
+
+    /// Reads a unary value, the amount of consecutive 1s starting at the offset.
+    fn read_unary(&self, offset: usize) -> usize {
+        let mut value = 0;
+        while self.read(offset + value) {
+            value += 1;
+        }
+        value
+    }
+
+    /// Renders all allocated cells, the marked index is surrounded by brackets.
+    fn get_string(&self, marked_index: Option<usize>) -> String {
+        render_cells(self, 0..self.get_bit_length(), marked_index)
+    }
+
+    /// Renders the cells within `radius` around the center (clamped at the start of the tape) and marks the center.
+    /// Unlike [`Self::get_string`] the output size doesn't depend on the size of the tape.
+    fn render_window(&self, center: usize, radius: usize) -> String {
+        render_cells(self, center.saturating_sub(radius)..center.saturating_add(radius).saturating_add(1), Some(center))
+    }
+}
+
+fn render_cells(tape: &impl Tape, range: Range<usize>, marked_index: Option<usize>) -> String {
+    let mut string = String::new();
+    for i in range {
+        if Some(i) == marked_index {
+            string.push('[');
+        } else {
+            string.push(' ');
+        }
+
+        if tape.read(i) {
+            string.push('1');
+        } else {
+            string.push('0');
+        }
+
+        if Some(i) == marked_index {
+            string.push(']');
+        } else {
+            string.push(' ');
+        }
+    }
+    string
+}
@@ -2,25 +2,51 @@ use std::thread::sleep;
 use std::time::Duration;
 use serde::{Deserialize, Serialize};
 use crate::enums::display_style::DisplayStyle;
+use crate::enums::halt_code::HaltCode;
 use crate::enums::movement::Movement;
+use crate::machine::compute::Encoding;
 use crate::machine::instruction::Instruction;
 use crate::machine::state::State;
+use crate::machine::tape::Tape;
 use crate::machine::turing_program::TuringProgram;
 use crate::machine::turing_tape::TuringTape;
 
+/// A turing machine which is generic over its tape backend, see [`Tape`].
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct TuringMachine {
-    pub tape: TuringTape,
+#[serde(bound = "")]
+pub struct TuringMachine<T: Tape = TuringTape> {
+    pub tape: T,
     pub head: usize,
     pub state: State, // Acts like a program counter
     pub program: TuringProgram,
     pub delay: Duration,
     pub debug_mode: bool,
     pub display_style: DisplayStyle,
+    /// How many cells around the head are rendered in the visual debug output.
+    #[serde(default = "default_window_radius")]
+    pub window_radius: usize,
 }
 
-impl TuringMachine {
-    pub fn with_tape(mut self, tape: TuringTape) -> Self {
+fn default_window_radius() -> usize {
+    20
+}
+
+impl<T: Tape> TuringMachine<T> {
+    /// Creates a machine with the given tape, which also decides the tape backend.
+    pub fn new(tape: T) -> Self {
+        Self {
+            tape,
+            head: 0,
+            state: State::default(),
+            program: TuringProgram::default(),
+            delay: Duration::from_millis(0),
+            debug_mode: false,
+            display_style: DisplayStyle::None,
+            window_radius: default_window_radius(),
+        }
+    }
+
+    pub fn with_tape(mut self, tape: T) -> Self {
         self.tape = tape;
         self
     }
@@ -37,8 +63,13 @@ impl TuringMachine {
         self
     }
 
+    pub fn with_window_radius(mut self, window_radius: usize) -> Self {
+        self.window_radius = window_radius;
+        self
+    }
+
     pub fn reset_state_information(&mut self) {
-        self.tape = TuringTape::default();
+        self.tape = T::default();
         self.state = State::default();
         self.head = 0;
     }
@@ -51,7 +82,7 @@ impl TuringMachine {
         self.program = program;
     }
 
-    pub fn set_tape(&mut self, tape: TuringTape) {
+    pub fn set_tape(&mut self, tape: T) {
         self.tape = tape;
     }
 
@@ -59,7 +90,12 @@ impl TuringMachine {
         self.head = head;
     }
 
-    pub fn read(&mut self) -> bool {
+    /// The head position relative to the cell the tape started at, which stays stable when the tape grows to the left.
+    pub fn get_position(&self) -> i64 {
+        self.head as i64 - self.tape.get_origin() as i64
+    }
+
+    pub fn read(&self) -> bool {
         self.tape.read(self.head)
     }
 
@@ -90,7 +126,7 @@ impl TuringMachine {
         let current_instruction = self.program.get(self.state, current_bit);
         let instruction = match current_instruction {
             None => {
-                if self.state.get() == usize::MAX {
+                if self.state.is_halt() {
                     return false;
                 } else {
                     panic!("Dangling state 'q{} σ={}'", self.state.get(), self.read())
@@ -109,12 +145,12 @@ impl TuringMachine {
                     );
                 },
                 DisplayStyle::Visual => {
-                    println!("{}", self.tape.get_string(Some(self.head)))
+                    println!("{}", self.tape.render_window(self.head, self.window_radius))
                 },
                 DisplayStyle::VisualFormal => {
                     println!(
                         "{} | Head: {} | {}",
-                        self.tape.get_string(Some(self.head)),
+                        self.tape.render_window(self.head, self.window_radius),
                         self.head,
                         instruction.get_formal_string()
                     )
@@ -143,31 +179,55 @@ impl TuringMachine {
         instruction.next_state
     }
 
-    pub fn run_program(&mut self) {
+    /// Runs until the machine halts and returns the code of the halt state it reached.
+    pub fn run_program(&mut self) -> HaltCode {
         while self.program_step() {
             if self.debug_mode {
                 sleep(self.delay);
             }
         }
+        self.state.get_halt_code().unwrap()
+    }
+
+    /// Like [`run_program`](TuringMachine::run_program), but gives up after the given number of steps and returns `None`.
+    pub fn run_program_with_budget(&mut self, step_budget: usize) -> Option<HaltCode> {
+        for _ in 0..step_budget {
+            if !self.program_step() {
+                return self.state.get_halt_code();
+            }
+            if self.debug_mode {
+                sleep(self.delay);
+            }
+        }
+        // The last step may have entered a halt state
+        self.state.get_halt_code()
+    }
+
+    /// Runs the program from the start on a fresh tape holding the input, see [`Encoding`] for where it's placed.
+    /// The program has to be a decider, which halts with [`HaltCode::Accept`] or [`HaltCode::Reject`].
+    ///
+    /// # Panics
+    /// If the program halts with any other code.
+    pub fn decides(&mut self, input: impl Encoding) -> bool {
+        self.reset_state_information();
+        input.write_input(&mut self.tape);
+        match self.run_program() {
+            HaltCode::Accept => true,
+            HaltCode::Reject => false,
+            code => panic!("Expected the program to accept or reject, but it halted with {}", code.get_name()),
+        }
     }
 }
 
 impl Default for TuringMachine {
     fn default() -> Self {
-        Self {
-            tape: TuringTape::default(),
-            head: 0,
-            state: State::default(),
-            program: TuringProgram::default(),
-            delay: Duration::from_millis(0),
-            debug_mode: false,
-            display_style: DisplayStyle::None,
-        }
+        Self::new(TuringTape::default())
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::machine::sparse_tape::SparseTape;
     use super::*;
 
     #[test]
@@ -216,9 +276,97 @@ mod tests {
 
         let mut tm = TuringMachine::default().with_tape(tape);
         tm.set_program(program);
-        tm.run_program();
+        assert_eq!(tm.run_program(), HaltCode::Halt);
         
         assert!(tm.read());
         assert_eq!(tm.head, 13);
     }
+
+    #[test]
+    fn test_run_program_with_budget() {
+        // Marks every cell to the right, never halting
+        let mut program = TuringProgram::default();
+        program.add_instruction(Instruction::new(State::new(0), false, true)
+            .with_movement(Movement::Right)
+            .with_next_state(State::new(0)));
+
+        let mut tm = TuringMachine::default().with_program(program);
+        assert_eq!(tm.run_program_with_budget(3), None);
+        assert_eq!(tm.head, 3);
+        assert_eq!(tm.tape.read_bit_string(0, 5), "11100");
+
+        // Halts on its second step
+        let mut program = TuringProgram::default();
+        program.add_instruction(Instruction::new(State::new(0), false, true)
+            .with_movement(Movement::Right)
+            .with_next_state(State::new(1)));
+        program.add_instruction(Instruction::new(State::new(1), false, true)
+            .with_next_state(State::halt_with(HaltCode::Accept)));
+
+        let mut tm = TuringMachine::default().with_program(program.clone());
+        assert_eq!(tm.run_program_with_budget(1), None);
+        assert_eq!(tm.tape.read_bit_string(0, 2), "10");
+        let mut tm = TuringMachine::default().with_program(program);
+        assert_eq!(tm.run_program_with_budget(2), Some(HaltCode::Accept));
+        assert_eq!(tm.tape.read_bit_string(0, 2), "11");
+    }
+
+    #[test]
+    fn test_sparse_tape_backend() {
+        let mut tape = SparseTape::default();
+        tape.set(3);
+
+        let mut tm = TuringMachine::new(tape);
+        tm.move_left();
+        tm.set();
+        assert_eq!(tm.head, 7);
+        assert_eq!(tm.get_position(), -1);
+        assert!(tm.tape.read(11));
+
+        tm.reset_state_information();
+        assert_eq!(tm.tape.get_bit_length(), 0);
+    }
+
+    #[test]
+    fn test_decides() {
+        // Accepts if the input starts with an even number of 1s
+        let mut program = TuringProgram::default();
+        for (state, other) in [(0, 1), (1, 0)] {
+            program.add_instruction(Instruction::new(State::new(state), true, true)
+                .with_movement(Movement::Right)
+                .with_next_state(State::new(other)));
+            let code = if state == 0 { HaltCode::Accept } else { HaltCode::Reject };
+            program.add_instruction(Instruction::new(State::new(state), false, false)
+                .with_next_state(State::halt_with(code)));
+        }
+
+        let mut tm = TuringMachine::default().with_program(program);
+        for (input, expected) in [("", true), ("1", false), ("110", true), ("1110", false), ("0111", true)] {
+            assert_eq!(tm.decides(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_halt_codes() {
+        for code in [HaltCode::Halt, HaltCode::Accept, HaltCode::Reject, HaltCode::Custom(0), HaltCode::Custom(u16::MAX)] {
+            let state = State::halt_with(code);
+            assert!(state.is_halt());
+            assert_eq!(state.get_halt_code(), Some(code));
+
+            let mut program = TuringProgram::default();
+            program.add_instruction(Instruction::new(State::new(0), false, true).with_next_state(state));
+            let mut tm = TuringMachine::default().with_program(program);
+            assert_eq!(tm.run_program(), code);
+        }
+        assert_eq!(State::halt_with(HaltCode::Halt), State::halt());
+        assert_eq!(State::new(usize::MAX - 3 - u16::MAX as usize - 1).get_halt_code(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "halted with HALT_7")]
+    fn test_decides_other_halt_code() {
+        let mut program = TuringProgram::default();
+        program.add_instruction(Instruction::new(State::new(0), false, false).with_next_state(State::halt_with(HaltCode::Custom(7))));
+        TuringMachine::default().with_program(program).decides("0");
+    }
 }
\ No newline at end of file
@@ -1,33 +1,262 @@
-use std::collections::HashMap;
-use std::fmt::{Display, Formatter};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Display, Formatter, Write};
 use serde::{Deserialize, Serialize};
 use crate::machine::instruction::Instruction;
 use crate::machine::state::State;
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct TuringProgram {
-    pub instructions: HashMap<(State, bool), Instruction>
+    pub instructions: HashMap<(State, bool), Instruction>,
+    /// Optional labels given to states by the compiler, used to group states in diagrams.
+    #[serde(default)]
+    pub labels: HashMap<State, String>,
 }
 
 impl TuringProgram {
     pub fn get(&self, state: State, read_bit: bool) -> Option<&Instruction> {
         self.instructions.get(&(state, read_bit))
     }
-    
+
     pub fn add_instruction(&mut self, instruction: Instruction) {
         self.instructions.insert(
             (instruction.current_state, instruction.read_bit),
             instruction
         );
     }
+
+    /// Merges `from` into `to`: all transitions into `from` lead to `to` instead and `from`'s instructions become `to`'s.
+    ///
+    /// # Panics
+    /// If both states have an instruction for the same bit.
+    pub fn redirect_state(&mut self, from: State, to: State) {
+        if from == to {
+            return;
+        }
+
+        for read_bit in [false, true] {
+            if let Some(instruction) = self.instructions.remove(&(from, read_bit)) {
+                assert!(
+                    !self.instructions.contains_key(&(to, read_bit)),
+                    "Can't redirect state {} into {}, both have an instruction for σ={}",
+                    from.get_name(),
+                    to.get_name(),
+                    read_bit as usize
+                );
+                self.add_instruction(Instruction { current_state: to, ..instruction });
+            }
+        }
+
+        for instruction in self.instructions.values_mut() {
+            if instruction.next_state == from {
+                instruction.next_state = to;
+            }
+        }
+
+        if let Some(label) = self.labels.remove(&from) {
+            self.labels.entry(to).or_insert(label);
+        }
+    }
+
+    /// Exchanges the numbers of two states, the behaviour of the program is unchanged apart from which state it starts in.
+    pub fn swap_states(&mut self, a: State, b: State) {
+        *self = self.map_states(|state| if state == a { b } else if state == b { a } else { state });
+    }
+
+    /// Combines the programs into one, which runs them one after another on the same tape.
+    /// Every program gets its own range of states and its halt transitions lead to the start of the next program.
+    pub fn link(programs: &[TuringProgram]) -> TuringProgram {
+        let mut linked = TuringProgram::default();
+        let mut offset = 0;
+
+        for (i, program) in programs.iter().enumerate() {
+            let mut relocated = program.relocate(offset);
+            offset += program.get_state_span();
+            if i < programs.len() - 1 {
+                relocated.redirect_state(State::halt(), State::new(offset));
+            }
+
+            linked.instructions.extend(relocated.instructions);
+            linked.labels.extend(relocated.labels);
+        }
+
+        linked
+    }
+
+    /// Shifts all states except halt by the given offset.
+    pub fn relocate(&self, offset: usize) -> TuringProgram {
+        self.map_states(|state| if state.is_halt() { state } else { State::new(state.get() + offset) })
+    }
+
+    /// How many state numbers the program occupies, which is one more than its highest state.
+    pub fn get_state_span(&self) -> usize {
+        self.get_states()
+            .into_iter()
+            .filter(|state| !state.is_halt())
+            .map(|state| state.get() + 1)
+            .max()
+            .unwrap_or(1)
+    }
+
+    /// Renames every state of the program, the mapping has to be injective.
+    pub fn map_states(&self, map: impl Fn(State) -> State) -> TuringProgram {
+        let instructions = self.instructions.values()
+            .map(|instruction| Instruction {
+                current_state: map(instruction.current_state),
+                next_state: map(instruction.next_state),
+                ..*instruction
+            })
+            .map(|instruction| ((instruction.current_state, instruction.read_bit), instruction))
+            .collect();
+        let labels = self.labels.iter()
+            .map(|(state, label)| (map(*state), label.clone()))
+            .collect();
+
+        TuringProgram { instructions, labels }
+    }
+
+    pub fn get_label(&self, state: State) -> Option<&str> {
+        self.labels.get(&state).map(|label| label.as_str())
+    }
+
+    pub fn set_label(&mut self, state: State, label: &str) {
+        self.labels.insert(state, label.to_string());
+    }
+
+    pub fn get_sorted_instructions(&self) -> Vec<&Instruction> {
+        let mut sorted_instructions: Vec<&Instruction> = self.instructions.values().collect();
+        sorted_instructions.sort_by_key(|instr| (instr.current_state.get(), instr.get_read_bit_number()));
+        sorted_instructions
+    }
+
+    /// All states which are either executed or transitioned to, sorted by their number.
+    pub fn get_states(&self) -> Vec<State> {
+        let states: BTreeSet<usize> = self.instructions
+            .values()
+            .flat_map(|instr| [instr.current_state.get(), instr.next_state.get()])
+            .chain([State::default().get()])
+            .collect();
+        states.into_iter().map(State::new).collect()
+    }
+
+    /// Renders the state diagram as a Graphviz DOT graph.
+    /// If `cluster_by_label` is set, states sharing a compiler label are grouped into a cluster.
+    pub fn to_dot(&self, cluster_by_label: bool) -> String {
+        let node_name = |state: State| match state.get_halt_code() {
+            Some(code) => code.get_name().to_lowercase(),
+            None => format!("q{}", state.get()),
+        };
+        let node_definition = |state: State| if state.is_halt() {
+            format!("{} [label=\"{}\", shape=doublecircle];", node_name(state), state.get_name())
+        } else {
+            format!("{} [label=\"{}\"];", node_name(state), state.get_name())
+        };
+
+        let mut dot = String::new();
+        writeln!(dot, "digraph TuringProgram {{").unwrap();
+        writeln!(dot, "    start [shape=point];").unwrap();
+        writeln!(dot, "    start -> {};", node_name(State::default())).unwrap();
+        writeln!(dot).unwrap();
+
+        let (clusters, unclustered) = self.group_states_by_label(cluster_by_label);
+        for (i, (label, states)) in clusters.iter().enumerate() {
+            writeln!(dot, "    subgraph cluster_{} {{", i).unwrap();
+            writeln!(dot, "        label=\"{}\";", escape_dot(label)).unwrap();
+            for state in states {
+                writeln!(dot, "        {}", node_definition(*state)).unwrap();
+            }
+            writeln!(dot, "    }}").unwrap();
+        }
+        for state in unclustered {
+            writeln!(dot, "    {}", node_definition(state)).unwrap();
+        }
+        writeln!(dot).unwrap();
+
+        for instruction in self.get_sorted_instructions() {
+            writeln!(
+                dot,
+                "    {} -> {} [label=\"{}\"];",
+                node_name(instruction.current_state),
+                node_name(instruction.next_state),
+                escape_dot(&instruction.get_diagram_label())
+            ).unwrap();
+        }
+        dot.push('}');
+        dot
+    }
+
+    /// Renders the state diagram as a Mermaid `stateDiagram-v2`, like the ones in the README.
+    /// If `cluster_by_label` is set, states sharing a compiler label are grouped into a composite state.
+    pub fn to_mermaid(&self, cluster_by_label: bool) -> String {
+        let node_name = |state: State| match state.get_halt_code() {
+            Some(code) => capitalize(&code.get_name().to_lowercase()),
+            None => format!("State{}", state.get()),
+        };
+        let node_definition = |state: State| format!("{}: {}", node_name(state), state.get_name());
+
+        let mut mermaid = String::new();
+        writeln!(mermaid, "stateDiagram-v2").unwrap();
+        writeln!(mermaid, "    [*] --> {}", node_name(State::default())).unwrap();
+        writeln!(mermaid).unwrap();
+
+        let (clusters, unclustered) = self.group_states_by_label(cluster_by_label);
+        for (i, (label, states)) in clusters.iter().enumerate() {
+            writeln!(mermaid, "    state \"{}\" as Group{} {{", label.replace('"', "'"), i).unwrap();
+            for state in states {
+                writeln!(mermaid, "        {}", node_definition(*state)).unwrap();
+            }
+            writeln!(mermaid, "    }}").unwrap();
+        }
+        for state in unclustered {
+            writeln!(mermaid, "    {}", node_definition(state)).unwrap();
+        }
+        writeln!(mermaid).unwrap();
+
+        for instruction in self.get_sorted_instructions() {
+            writeln!(
+                mermaid,
+                "    {} --> {}: {}",
+                node_name(instruction.current_state),
+                node_name(instruction.next_state),
+                instruction.get_diagram_label()
+            ).unwrap();
+        }
+        mermaid.pop();
+        mermaid
+    }
+
+    /// Splits all states into clusters of equally labeled states (sorted by label) and the remaining states.
+    fn group_states_by_label(&self, cluster_by_label: bool) -> (Vec<(String, Vec<State>)>, Vec<State>) {
+        let mut clusters: Vec<(String, Vec<State>)> = Vec::new();
+        let mut unclustered = Vec::new();
+
+        for state in self.get_states() {
+            let label = self.get_label(state).filter(|_| cluster_by_label && !state.is_halt());
+            match label {
+                Some(label) => match clusters.iter_mut().find(|(existing, _)| existing == label) {
+                    Some((_, states)) => states.push(state),
+                    None => clusters.push((label.to_string(), vec![state])),
+                },
+                None => unclustered.push(state),
+            }
+        }
+
+        clusters.sort_by(|(a, _), (b, _)| a.cmp(b));
+        (clusters, unclustered)
+    }
+}
+
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+fn escape_dot(string: &str) -> String {
+    string.replace('\\', "\\\\").replace('"', "\\\"")
 }
 
 impl Display for TuringProgram {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
-        let mut sorted_instructions: Vec<&Instruction> = self.instructions.values().collect();
-        sorted_instructions.sort_by_key(|instr| (instr.current_state.get(), instr.get_read_bit_number()));
-        
-        for (i, instruction) in sorted_instructions.iter().enumerate() {
+        for (i, instruction) in self.get_sorted_instructions().iter().enumerate() {
             if i > 0 {
                 writeln!(f)?;
             }
@@ -35,4 +264,148 @@ impl Display for TuringProgram {
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::enums::halt_code::HaltCode;
+    use crate::enums::movement::Movement;
+    use crate::machine::turing_machine::TuringMachine;
+    use super::*;
+
+    /// Example 3 of the README: move right until a 1 is found, then halt.
+    fn build_scan_program() -> TuringProgram {
+        let mut program = TuringProgram::default();
+        program.add_instruction(
+            Instruction::new(State::new(0), false, false)
+                .with_movement(Movement::Right)
+                .with_next_state(State::new(0))
+        );
+        program.add_instruction(
+            Instruction::new(State::new(0), true, true)
+                .with_next_state(State::halt())
+        );
+        program
+    }
+
+    #[test]
+    fn test_to_mermaid() {
+        let expected = "\
+stateDiagram-v2
+    [*] --> State0
+
+    State0: 0
+    Halt: HALT
+
+    State0 --> State0: read 0→write 0, move R
+    State0 --> Halt: read 1→write 1, stay";
+
+        assert_eq!(build_scan_program().to_mermaid(false), expected);
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let expected = "\
+digraph TuringProgram {
+    start [shape=point];
+    start -> q0;
+
+    q0 [label=\"0\"];
+    halt [label=\"HALT\", shape=doublecircle];
+
+    q0 -> q0 [label=\"read 0→write 0, move R\"];
+    q0 -> halt [label=\"read 1→write 1, stay\"];
+}";
+
+        assert_eq!(build_scan_program().to_dot(false), expected);
+    }
+
+    #[test]
+    fn test_halt_code_nodes() {
+        let mut program = TuringProgram::default();
+        program.add_instruction(Instruction::new(State::new(0), false, false).with_next_state(State::halt_with(HaltCode::Reject)));
+        program.add_instruction(Instruction::new(State::new(0), true, true).with_next_state(State::halt_with(HaltCode::Custom(3))));
+
+        let dot = program.to_dot(false);
+        assert!(dot.contains("\n    reject [label=\"REJECT\", shape=doublecircle];\n"));
+        assert!(dot.contains("\n    q0 -> halt_3 [label=\"read 1→write 1, stay\"];\n"));
+        let mermaid = program.to_mermaid(false);
+        assert!(mermaid.contains("\n    Reject: REJECT\n"));
+        assert!(mermaid.contains("\n    Halt_3: HALT_3\n"));
+    }
+
+    #[test]
+    fn test_redirect_state() {
+        let mut program = build_scan_program();
+        program.add_instruction(Instruction::new(State::new(1), false, true).with_next_state(State::new(0)));
+        program.redirect_state(State::new(0), State::new(2));
+
+        assert!(program.get(State::new(0), false).is_none());
+        assert_eq!(program.get(State::new(2), false).unwrap().next_state, State::new(2));
+        assert_eq!(program.get(State::new(2), true).unwrap().next_state, State::halt());
+        assert_eq!(program.get(State::new(1), false).unwrap().next_state, State::new(2));
+    }
+
+    #[test]
+    fn test_swap_states() {
+        let mut program = build_scan_program();
+        program.add_instruction(Instruction::new(State::new(1), true, true).with_next_state(State::new(0)));
+        program.set_label(State::new(1), "check");
+        program.swap_states(State::new(0), State::new(1));
+
+        assert_eq!(program.get(State::new(1), false).unwrap().next_state, State::new(1));
+        assert_eq!(program.get(State::new(1), true).unwrap().next_state, State::halt());
+        assert_eq!(program.get(State::new(0), true).unwrap().next_state, State::new(1));
+        assert_eq!(program.get_label(State::new(0)), Some("check"));
+    }
+
+    #[test]
+    fn test_link() {
+        let mut mark = TuringProgram::default();
+        mark.add_instruction(Instruction::new(State::new(0), false, true).with_next_state(State::new(1)));
+        mark.add_instruction(Instruction::new(State::new(1), true, true).with_movement(Movement::Left).with_next_state(State::halt()));
+        mark.set_label(State::new(1), "mark");
+
+        let linked = TuringProgram::link(&[mark, build_scan_program()]);
+        assert_eq!(linked.instructions.len(), 4);
+        assert_eq!(linked.get(State::new(1), true).unwrap().next_state, State::new(2));
+        assert_eq!(linked.get(State::new(2), false).unwrap().next_state, State::new(2));
+        assert_eq!(linked.get(State::new(2), true).unwrap().next_state, State::halt());
+        assert_eq!(linked.get_label(State::new(1)), Some("mark"));
+
+        // Marks the start cell, moves left and scans right until it finds the mark again
+        let mut tm = TuringMachine::default().with_program(linked);
+        tm.run_program();
+        assert_eq!(tm.get_position(), 0);
+        assert!(tm.read());
+    }
+
+    #[test]
+    fn test_relocate() {
+        let program = build_scan_program().relocate(5);
+        assert_eq!(program.get_states(), vec![State::new(0), State::new(5), State::halt()]);
+        assert_eq!(program.get_state_span(), 6);
+        assert_eq!(program.get(State::new(5), false).unwrap().next_state, State::new(5));
+    }
+
+    #[test]
+    fn test_cluster_by_label() {
+        let mut program = build_scan_program();
+        program.add_instruction(Instruction::new(State::new(1), false, true).with_next_state(State::new(2)));
+        program.set_label(State::new(0), "scan");
+        program.set_label(State::new(1), "mark");
+        program.set_label(State::new(2), "mark");
+
+        let dot = program.to_dot(true);
+        assert!(dot.contains("    subgraph cluster_0 {\n        label=\"mark\";\n        q1 [label=\"1\"];\n        q2 [label=\"2\"];\n    }"));
+        assert!(dot.contains("    subgraph cluster_1 {\n        label=\"scan\";\n        q0 [label=\"0\"];\n    }"));
+
+        let mermaid = program.to_mermaid(true);
+        assert!(mermaid.contains("    state \"mark\" as Group0 {\n        State1: 1\n        State2: 2\n    }"));
+        assert!(mermaid.contains("    state \"scan\" as Group1 {\n        State0: 0\n    }"));
+        assert!(mermaid.contains("\n    Halt: HALT\n"));
+
+        // Labels are ignored when not clustering
+        assert!(!program.to_dot(false).contains("subgraph"));
+    }
+}
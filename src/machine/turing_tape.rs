@@ -1,19 +1,17 @@
 use std::collections::VecDeque;
 use serde::{Deserialize, Serialize};
+use crate::machine::tape::Tape;
 
+/// The dense tape backend, all allocated cells are stored in one contiguous deque of bytes.
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct TuringTape {
     tape: VecDeque<u8>,
+    /// How many bits were allocated to the left, the bit index of the original first cell.
+    #[serde(default)]
+    origin: usize,
 }
 
 impl TuringTape {
-    pub fn allocate_till_bit_index(&mut self, bit_index: usize) {
-        let byte_index = bit_index / 8;
-        if byte_index >= self.tape.len() {
-            self.allocate_right(byte_index - self.tape.len() + 1)
-        }
-    }
-
     pub fn get_byte_index_and_position(&mut self, bit_index: usize) -> (usize, usize) {
         let byte_index = bit_index / 8;
         if byte_index >= self.tape.len() {
@@ -24,63 +22,61 @@ impl TuringTape {
         (byte_index, position)
     }
 
-    pub fn read(&mut self, bit_index: usize) -> bool {
-        let (byte_index, position) = self.get_byte_index_and_position(bit_index);
-        let byte = self.tape[byte_index];
-        (byte & (1 << position)) != 0
+    pub fn allocate_right(&mut self, size: usize) {
+        self.tape.resize(self.tape.len() + size, 0);
+    }
+}
+
+impl Tape for TuringTape {
+    fn read(&self, bit_index: usize) -> bool {
+        match self.tape.get(bit_index / 8) {
+            Some(byte) => (byte & (1 << (bit_index % 8))) != 0,
+            None => false,
+        }
     }
 
-    pub fn set(&mut self, bit_index: usize) {
+    fn set(&mut self, bit_index: usize) {
         let (byte_index, position) = self.get_byte_index_and_position(bit_index);
         let byte = &mut self.tape[byte_index];
         *byte |= 1 << position;
     }
 
-    pub fn unset(&mut self, bit_index: usize) {
+    fn unset(&mut self, bit_index: usize) {
         let (byte_index, position) = self.get_byte_index_and_position(bit_index);
         let byte = &mut self.tape[byte_index];
         *byte &= !(1 << position);
     }
 
-    pub fn get_string(&mut self, marked_index: Option<usize>) -> String {
-        let mut string = String::new();
-        for i in 0..self.tape.len() * 8 {
-            if Some(i) == marked_index {
-                string.push('[');
-            } else {
-                string.push(' ');
-            }
-
-            if self.read(i) {
-                string.push('1');
-            } else {
-                string.push('0');
-            }
-
-            if Some(i) == marked_index {
-                string.push(']');
-            } else {
-                string.push(' ');
-            }
+    fn allocate_till_bit_index(&mut self, bit_index: usize) {
+        let byte_index = bit_index / 8;
+        if byte_index >= self.tape.len() {
+            self.allocate_right(byte_index - self.tape.len() + 1)
         }
-        string
     }
 
-    pub fn allocate_left(&mut self, size: usize) {
+    fn allocate_left(&mut self, size: usize) {
         for _ in 0..size {
             self.tape.push_front(0);
         }
+        self.origin += size * 8;
     }
 
-    pub fn allocate_right(&mut self, size: usize) {
-        for _ in 0..size {
-            self.tape.push_back(0);
-        }
+    fn get_bit_length(&self) -> usize {
+        self.tape.len() * 8
+    }
+
+    fn get_origin(&self) -> usize {
+        self.origin
+    }
+
+    fn get_memory_usage(&self) -> usize {
+        self.tape.capacity()
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::enums::endianness::Endianness;
     use super::*;
 
     #[test]
@@ -101,4 +97,82 @@ mod tests {
         tape.unset(3);
         assert!(!tape.read(3));
     }
+
+    #[test]
+    fn test_read_does_not_allocate() {
+        let tape = TuringTape::default();
+        assert!(!tape.read(1000));
+        assert_eq!(tape.get_bit_length(), 0);
+    }
+
+    #[test]
+    fn test_render_window() {
+        let tape = TuringTape::from_bit_string("0110_1");
+        assert_eq!(tape.get_string(Some(2)), " 0  1 [1] 0  1  0  0  0 ");
+        assert_eq!(tape.render_window(2, 1), " 1 [1] 0 ");
+        assert_eq!(tape.render_window(0, 2), "[0] 1  1 ");
+        assert_eq!(tape.render_window(1_000_000, 1), " 0 [0] 0 ");
+    }
+
+    #[test]
+    fn test_bit_strings() {
+        let mut tape = TuringTape::from_bit_string("0110_1");
+        assert!(!tape.read(0));
+        assert!(tape.read(1));
+        assert!(tape.read(2));
+        assert!(!tape.read(3));
+        assert!(tape.read(4));
+        assert_eq!(tape.read_bit_string(0, 8), "01101000");
+
+        tape.write_bit_string(6, "11");
+        assert_eq!(tape.read_bit_string(4, 4), "1011");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_bit_string() {
+        TuringTape::from_bit_string("01x");
+    }
+
+    #[test]
+    fn test_bytes() {
+        let tape = TuringTape::from_bytes(&[0b0000_0101, 0xFF]);
+        assert_eq!(tape.read_bit_string(0, 16), "1010000011111111");
+        assert_eq!(tape.read_bytes(0, 2), vec![0b0000_0101, 0xFF]);
+        assert_eq!(tape.read_bytes(1, 1), vec![0b1000_0010]);
+    }
+
+    #[test]
+    fn test_unsigned() {
+        let mut tape = TuringTape::from_unsigned(6, 4, Endianness::Little, 2);
+        assert_eq!(tape.read_bit_string(0, 8), "00011000");
+        assert_eq!(tape.read_unsigned(2, 4, Endianness::Little), 6);
+
+        tape.write_unsigned(8, 6, 4, Endianness::Big);
+        assert_eq!(tape.read_bit_string(8, 4), "0110");
+        assert_eq!(tape.read_unsigned(8, 4, Endianness::Big), 6);
+
+        tape.write_unsigned(16, u64::MAX, 64, Endianness::Big);
+        assert_eq!(tape.read_unsigned(16, 64, Endianness::Little), u64::MAX);
+    }
+
+    #[test]
+    fn test_unary() {
+        let tape = TuringTape::from_unary(3, 1);
+        assert_eq!(tape.read_bit_string(0, 6), "011100");
+        assert_eq!(tape.read_unary(1), 3);
+        assert_eq!(tape.read_unary(0), 0);
+    }
+
+    #[test]
+    fn test_allocate_left_moves_origin() {
+        let mut tape = TuringTape::default();
+        tape.set(2);
+        assert_eq!(tape.get_origin(), 0);
+
+        tape.allocate_left(2);
+        assert_eq!(tape.get_origin(), 16);
+        assert_eq!(tape.get_bit_length(), 24);
+        assert!(tape.read(tape.get_origin() + 2));
+    }
 }
\ No newline at end of file
@@ -1,32 +1,24 @@
 use std::time::Duration;
-use crate::compiler::layers::base::BaseLayer;
-use crate::compiler::layers::pattern::PatternLayer;
-use crate::compiler::layers::primitive::PrimitiveLayer;
-use crate::compiler::layers::program_builder::ProgramBuilder;
-use crate::compiler::structures::pattern::Pattern;
-use crate::compiler::turing_compiler::TuringCompiler;
-use crate::enums::display_style::DisplayStyle;
-use crate::enums::movement::Movement;
-use crate::machine::turing_machine::TuringMachine;
-use crate::machine::turing_program::TuringProgram;
-
-mod enums;
-mod machine;
-mod compiler;
+use simple_turing_machine::compiler::layers::base::BaseLayer;
+use simple_turing_machine::compiler::layers::pattern::PatternLayer;
+use simple_turing_machine::compiler::layers::primitive::PrimitiveLayer;
+use simple_turing_machine::compiler::layers::program_builder::ProgramBuilder;
+use simple_turing_machine::compiler::structures::pattern::Pattern;
+use simple_turing_machine::compiler::turing_compiler::TuringCompiler;
+use simple_turing_machine::enums::display_style::DisplayStyle;
+use simple_turing_machine::enums::movement::Movement;
+use simple_turing_machine::machine::turing_machine::TuringMachine;
+use simple_turing_machine::machine::turing_program::TuringProgram;
 
 fn main() {
-    let current_programs = [
+    let program = TuringProgram::link(&[
         build_mark_start_do_stuff_find_start()
-    ];
-    
+    ]);
+
     let mut tm = TuringMachine::default()
+        .with_program(program)
         .with_debug_mode(DisplayStyle::VisualFormal, Duration::from_millis(100));
-
-    for program in current_programs {
-        tm.set_program(program);
-        tm.run_program();
-        tm.reset_state_but_persist_tape();
-    }
+    tm.run_program();
 }
 
 fn build_mark_start_do_stuff_find_start() -> TuringProgram {
@@ -48,6 +40,7 @@ fn build_mark_start_do_stuff_find_start() -> TuringProgram {
     compiler.get_program()
 }
 
+#[allow(dead_code)]
 fn build_set_bit_x_and_find_it_again(x: usize) -> TuringProgram {
     let mut compiler = TuringCompiler::default();
 
@@ -66,6 +59,7 @@ fn build_set_bit_x_and_find_it_again(x: usize) -> TuringProgram {
     compiler.get_program()
 }
 
+#[allow(dead_code)]
 fn build_set_bit_x_one(x: usize) -> TuringProgram {
     let mut compiler = TuringCompiler::default();
 
@@ -91,6 +85,7 @@ fn build_set_bit_x_one(x: usize) -> TuringProgram {
     compiler.get_program()
 }
 
+#[allow(dead_code)]
 fn build_move_right_till_one() -> TuringProgram {
     let mut compiler = TuringCompiler::default();
 
@@ -0,0 +1,33 @@
+/// A tiny xorshift generator, so randomized tests are reproducible without extra dependencies.
+pub struct XorShift(u64);
+
+impl XorShift {
+    pub fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A random value which fits into the given number of bits.
+    pub fn next_bits(&mut self, width: usize) -> u64 {
+        self.next_u64() & get_mask(width)
+    }
+}
+
+pub fn get_mask(width: usize) -> u64 {
+    if width >= 64 {
+        u64::MAX
+    } else {
+        (1 << width) - 1
+    }
+}
+
+/// The cells of a string of `0`s and `1`s.
+pub fn to_bits(bits: &str) -> Vec<bool> {
+    bits.chars().map(|bit| bit == '1').collect()
+}